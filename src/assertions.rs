@@ -0,0 +1,95 @@
+use anyhow::Result;
+use crate::browser::frame::FrameScope;
+use crate::browser::selector::{js_string, query_all_expression, query_expression};
+use crate::browser::waiter::url_matcher;
+use crate::models::assertion::Assertion;
+
+const MAX_ACTUAL_LEN: usize = 200;
+
+pub struct AssertionCheck{
+  pub passed: bool,
+  pub expected: String,
+  pub actual: String,
+}
+
+/// element checks run in the step's frame; url and title always describe the top-level page
+pub async fn check_assertion(scope: &FrameScope, assertion: &Assertion) -> Result<AssertionCheck>{
+  let check = match assertion{
+    Assertion::Text{value, selector} => {
+      let text = match selector{
+        Some(selector) => query(scope, selector, "el.innerText").await?,
+        None => scope.evaluate("document.body ? document.body.innerText : ''").await?
+          .value()
+          .and_then(|v| v.as_str())
+          .map(String::from),
+      };
+      AssertionCheck{
+        passed: text.as_deref().is_some_and(|text| text.contains(value.as_str())),
+        expected: format!("contains '{}'", value),
+        actual: text.map(|text| truncate(&text)).unwrap_or_else(|| "<element not found>".to_string()),
+      }
+    }
+    Assertion::Count{selector, equals, min, max} => {
+      if equals.is_none() && min.is_none() && max.is_none(){
+        anyhow::bail!("count assertion needs equals, min or max");
+      }
+      let count: usize = scope
+        .evaluate(format!("{}.length", query_all_expression(selector)))
+        .await?
+        .into_value()?;
+      let passed = equals.is_none_or(|n| count == n)
+        && min.is_none_or(|n| count >= n)
+        && max.is_none_or(|n| count <= n);
+      let expected = [
+        equals.map(|n| format!("== {}", n)),
+        min.map(|n| format!(">= {}", n)),
+        max.map(|n| format!("<= {}", n)),
+      ].into_iter().flatten().collect::<Vec<_>>().join(", ");
+      AssertionCheck{passed, expected, actual: count.to_string()}
+    }
+    Assertion::Attribute{selector, name, value} => {
+      let attribute = query(scope, selector, &format!("el.getAttribute({})", js_string(name))).await?;
+      AssertionCheck{
+        passed: attribute.as_deref() == Some(value.as_str()),
+        expected: value.clone(),
+        actual: attribute.unwrap_or_else(|| "<missing>".to_string()),
+      }
+    }
+    Assertion::Url{url: pattern} => {
+      let url = scope.page().url().await?.unwrap_or_default();
+      let script = format!("({})({})", url_matcher(pattern)?, serde_json::Value::from(url.as_str()));
+      AssertionCheck{
+        passed: scope.page().evaluate(script).await?.value().and_then(|v| v.as_bool()) == Some(true),
+        expected: pattern.to_string(),
+        actual: url,
+      }
+    }
+    Assertion::Title{value} => {
+      let title = scope.page().get_title().await?.unwrap_or_default();
+      AssertionCheck{
+        passed: title.contains(value.as_str()),
+        expected: format!("contains '{}'", value),
+        actual: title,
+      }
+    }
+  };
+  Ok(check)
+}
+
+/// evaluates `expression` against the first match of `selector` bound as `el`,
+/// returning `None` when nothing matches or the expression yields null
+async fn query(scope: &FrameScope, selector: &str, expression: &str) -> Result<Option<String>>{
+  let script = format!(
+    "(() => {{ const el = {}; return el ? {} : null; }})()",
+    query_expression(selector),
+    expression
+  );
+  Ok(scope.evaluate(script).await?.value().and_then(|v| v.as_str()).map(String::from))
+}
+
+fn truncate(text: &str) -> String{
+  match text.char_indices().nth(MAX_ACTUAL_LEN){
+    Some((idx, _)) => format!("{}…", &text[..idx]),
+    None => text.to_string(),
+  }
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use anyhow::{Context, Result};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use crate::models::{auth_profile::AuthProfile, storage_state::StorageState};
+
+/// auth profiles and the sessions saved from them, kept in one directory
+pub struct AuthStore{
+  dir: PathBuf,
+  logins: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+impl AuthStore{
+  pub fn new(dir: PathBuf) -> Self{
+    Self{dir, logins: Mutex::new(HashMap::new())}
+  }
+
+  pub async fn profile(&self, name: &str) -> Result<AuthProfile>{
+    let path = self.dir.join(format!("{}.yaml", name));
+    let yaml = tokio::fs::read_to_string(&path)
+      .await
+      .with_context(|| format!("auth profile not found: {}", path.display()))?;
+    serde_yaml::from_str(&yaml)
+      .map_err(|e| anyhow::anyhow!("failed to parse auth profile {}: {}", path.display(), e))
+  }
+
+  pub async fn state(&self, name: &str) -> Result<Option<StorageState>>{
+    let path = self.state_path(name);
+    if !tokio::fs::try_exists(&path).await?{
+      return Ok(None);
+    }
+    let json = tokio::fs::read_to_string(&path).await?;
+    let state = serde_json::from_str(&json)
+      .with_context(|| format!("failed to parse storage state {}", path.display()))?;
+    Ok(Some(state))
+  }
+
+  pub async fn save_state(&self, name: &str, state: &StorageState) -> Result<()>{
+    let path = self.state_path(name);
+    tokio::fs::write(&path, serde_json::to_string_pretty(state)?)
+      .await
+      .with_context(|| format!("failed to write storage state {}", path.display()))
+  }
+
+  /// held while logging in, so concurrent tasks of one profile log in only once
+  pub async fn lock(&self, name: &str) -> OwnedMutexGuard<()>{
+    let lock = self.logins.lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .entry(name.to_string())
+      .or_default()
+      .clone();
+    lock.lock_owned().await
+  }
+
+  fn state_path(&self, name: &str) -> PathBuf{
+    self.dir.join(format!("{}.state.json", name))
+  }
+}
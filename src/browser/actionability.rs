@@ -0,0 +1,122 @@
+use anyhow::Result;
+use crate::browser::frame::FrameScope;
+use crate::browser::selector::query_expression;
+use crate::browser::waiter::wait_for;
+use crate::models::point::Point;
+use crate::models::task_error::{ErrorKind, TaskError};
+
+/// which conditions an element must meet before it is interacted with
+#[derive(Debug, Clone, Copy)]
+pub struct Checks{
+  pub visible: bool,
+  pub stable: bool,
+  pub enabled: bool,
+  pub receives_events: bool,
+}
+
+impl Checks{
+  pub const CLICK: Checks = Checks{visible: true, stable: true, enabled: true, receives_events: true};
+  pub const HOVER: Checks = Checks{visible: true, stable: true, enabled: false, receives_events: true};
+  pub const INPUT: Checks = Checks{visible: true, stable: false, enabled: true, receives_events: false};
+  pub const DROP_TARGET: Checks = Checks{visible: true, stable: true, enabled: false, receives_events: false};
+  pub const VISIBLE: Checks = Checks{visible: true, stable: false, enabled: false, receives_events: false};
+  /// only waits for the element to be in the document, as for hidden file inputs
+  pub const ATTACHED: Checks = Checks{visible: false, stable: false, enabled: false, receives_events: false};
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Actionability{
+  pub timeout_ms: u64,
+  /// skips every check and acts on the element as found
+  pub force: bool,
+}
+
+/// waits until `selector` is attached and meets `checks`, scrolling it into view first when it
+/// must be visible, and
+/// returns the viewport point to interact with: its center, or `offset` from its top-left corner.
+/// inside an iframe the point is translated to the top-level viewport. the checks run in the
+/// page's waiter, so an element that is ready costs a single round trip
+pub async fn wait_for_actionable(
+  scope: &FrameScope,
+  selector: &str,
+  offset: Option<Point>,
+  checks: Checks,
+  options: &Actionability,
+) -> Result<Point>{
+  let body = format!(
+    r#"
+      const el = {query};
+      if(!el) throw new Error('element not found');
+      const checks = {checks};
+      const offset = {offset};
+      const frame = () => new Promise(resolve => requestAnimationFrame(resolve));
+      const describe = (node) => {{
+        if(!node) return 'nothing';
+        let text = node.tagName.toLowerCase();
+        if(node.id) text += '#' + node.id;
+        for(const cls of Array.from(node.classList).slice(0, 2)) text += '.' + cls;
+        return text;
+      }};
+
+      if(checks.visible) el.scrollIntoView({{ block: 'center', inline: 'center', behavior: 'instant' }});
+      const before = el.getBoundingClientRect();
+      if(checks.stable){{ await frame(); await frame(); }}
+      const rect = el.getBoundingClientRect();
+      const style = window.getComputedStyle(el);
+
+      if(checks.visible && (rect.width === 0 || rect.height === 0 ||
+          style.visibility === 'hidden' || style.display === 'none')){{
+        throw new Error('element is not visible');
+      }}
+      if(checks.stable && (before.x !== rect.x || before.y !== rect.y ||
+          before.width !== rect.width || before.height !== rect.height)){{
+        throw new Error('element is still moving');
+      }}
+      if(checks.enabled && (el.disabled || el.closest('fieldset[disabled]') ||
+          el.getAttribute('aria-disabled') === 'true')){{
+        throw new Error('element is disabled');
+      }}
+
+      const x = offset ? rect.left + offset.x : rect.left + rect.width / 2;
+      const y = offset ? rect.top + offset.y : rect.top + rect.height / 2;
+      if(checks.receives_events){{
+        const hit = el.getRootNode().elementFromPoint
+          ? el.getRootNode().elementFromPoint(x, y)
+          : document.elementFromPoint(x, y);
+        if(hit !== el && !el.contains(hit)){{
+          throw new Error('element is obscured by ' + describe(hit));
+        }}
+      }}
+      return {{ x, y }};
+    "#,
+    query = query_expression(selector),
+    checks = serde_json::json!({
+      "visible": checks.visible,
+      "stable": checks.stable,
+      "enabled": checks.enabled,
+      "receives_events": checks.receives_events,
+    }),
+    offset = serde_json::to_string(&offset)?,
+  );
+
+  let what = format!("{} to be actionable", selector);
+  let point = match wait_for(scope, &body, options.timeout_ms, &what).await{
+    Ok(point) => point,
+    Err(e) => {
+      let Some(error) = e.downcast_ref::<TaskError>() else{
+        return Err(e);
+      };
+      // an element that never showed up is reported as missing rather than as a slow one
+      let attached = scope.evaluate(format!("!!{}", query_expression(selector))).await
+        .ok()
+        .and_then(|result| result.value().and_then(|v| v.as_bool()));
+      let kind = if attached == Some(false){ErrorKind::SelectorNotFound}else{error.kind};
+      return Err(TaskError::new(kind, error.message.clone()).with_selector(selector).into());
+    }
+  };
+
+  scope.to_viewport(Point{
+    x: point["x"].as_f64().unwrap_or_default(),
+    y: point["y"].as_f64().unwrap_or_default(),
+  }).await
+}
@@ -1,30 +1,81 @@
+use std::ops::Deref;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
-use std::collections::HashMap;
-use futures::StreamExt;
+use futures::{Stream, StreamExt, stream};
 use anyhow::{Context, Result};
 use chromiumoxide::{
   browser::{Browser, BrowserConfig},
   cdp::browser_protocol::{
-    network::SetCookieParams,
+    browser::BrowserContextId,
+    network::{CookieParam, CookieSameSite, TimeSinceEpoch},
     emulation::SetDeviceMetricsOverrideParamsBuilder,
+    page::{
+      AddScriptToEvaluateOnNewDocumentParams,
+      EventFrameNavigated,
+      RemoveScriptToEvaluateOnNewDocumentParams,
+      ScriptIdentifier,
+    },
+    storage::{GetCookiesParams, SetCookiesParams},
+    target::{
+      CloseTargetParams,
+      CreateBrowserContextParams,
+      CreateTargetParams,
+      EventAttachedToTarget,
+      EventTargetCreated,
+      EventTargetDestroyed,
+      GetTargetsParams,
+      TargetId,
+    },
   },
   Page,
 };
-use tokio::time::sleep;
-use crate::models::cookie::Cookie;
+use tokio::{sync::{Mutex, watch}, task::JoinHandle, time::sleep};
+use crate::models::{
+  cookie::{Cookie, SameSite},
+  storage_state::OriginStorage,
+};
 
+/// the browser tasks run in. chrome is relaunched by `relaunch_if_crashed` once its devtools
+/// connection is lost; pages and contexts of the crashed browser are gone with it
 pub struct BrowserController{
-  browser: Browser,
+  launched: RwLock<Arc<Launched>>,
+  relaunching: Mutex<()>,
   viewport_width: u32,
   viewport_height: u32,
 }
 
-impl BrowserController{
-  pub async fn new() -> Result<Self>{
-    Self::with_viewport(1920, 1080).await
+/// one launch of chrome, and whether its devtools connection is still up
+struct Launched{
+  browser: Browser,
+  connected: watch::Receiver<bool>,
+  handler: JoinHandle<()>,
+}
+
+impl Deref for Launched{
+  type Target = Browser;
+
+  fn deref(&self) -> &Browser{
+    &self.browser
   }
+}
+
+impl Drop for Launched{
+  fn drop(&mut self){
+    self.handler.abort();
+  }
+}
 
+impl BrowserController{
   pub async fn with_viewport(width: u32, height: u32) -> Result<Self>{
+    Ok(Self{
+      launched: RwLock::new(Arc::new(Self::launch(width, height).await?)),
+      relaunching: Mutex::new(()),
+      viewport_width: width,
+      viewport_height: height,
+    })
+  }
+
+  async fn launch(width: u32, height: u32) -> Result<Launched>{
     let(browser, mut handler) = Browser::launch(
       BrowserConfig::builder()
         .window_size(width, height)
@@ -43,24 +94,115 @@ impl BrowserController{
     .await
     .context("failed to launch browser")?;
 
-    tokio::spawn(async move{
+    // the handler ends once the connection to chrome is gone, which is how a crash shows up
+    let (connected_tx, connected) = watch::channel(true);
+    let handler = tokio::spawn(async move{
       loop{
         if handler.next().await.is_none(){
           break;
         }
       }
+      connected_tx.send_replace(false);
     });
 
-    Ok(Self{
-      browser,
-      viewport_width: width,
-      viewport_height: height,
-    })
+    Ok(Launched{browser, connected, handler})
+  }
+
+  fn browser(&self) -> Arc<Launched>{
+    self.launched.read().unwrap_or_else(|e| e.into_inner()).clone()
+  }
+
+  fn is_connected(&self) -> bool{
+    *self.browser().connected.borrow()
+  }
+
+  /// changes to false once the connection to the current browser is lost
+  pub fn connection(&self) -> watch::Receiver<bool>{
+    self.browser().connected.clone()
+  }
+
+  /// launches a new browser in place of one whose connection was lost. tasks that saw the
+  /// crash together relaunch it once; returns whether this call did
+  pub async fn relaunch_if_crashed(&self) -> Result<bool>{
+    let _relaunching = self.relaunching.lock().await;
+    if self.is_connected(){
+      return Ok(false);
+    }
+
+    let launched = Self::launch(self.viewport_width, self.viewport_height)
+      .await
+      .context("failed to relaunch crashed browser")?;
+    // the old process is killed once the last task holding it lets go
+    *self.launched.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(launched);
+    Ok(true)
+  }
+
+  /// creates an isolated, incognito-style context with its own cookies, storage and cache
+  pub async fn create_context(&self) -> Result<BrowserContextId>{
+    self.browser().create_browser_context(CreateBrowserContextParams::default())
+      .await
+      .context("failed to create browser context")
+  }
+
+  pub async fn dispose_context(&self, context: BrowserContextId) -> Result<()>{
+    self.browser().dispose_browser_context(context)
+      .await
+      .context("failed to dispose browser context")
   }
 
-  pub async fn new_page(&self) -> Result<Page>{
-    let page = self.browser.new_page("about:blank").await?;
+  pub async fn new_page(&self, context: &BrowserContextId) -> Result<Page>{
+    let params = CreateTargetParams::builder()
+      .url("about:blank")
+      .browser_context_id(context.clone())
+      .build()
+      .map_err(|e| anyhow::anyhow!("Failed to build create target params: {}", e))?;
+    let page = self.browser().new_page(params).await?;
+    self.emulate_viewport(&page).await?;
+    Ok(page)
+  }
 
+  /// ids of the open tabs and popups in `context`
+  pub async fn context_targets(&self, context: &BrowserContextId) -> Result<Vec<TargetId>>{
+    let targets = self.browser().execute(GetTargetsParams::default())
+      .await
+      .context("failed to list targets")?
+      .result
+      .target_infos;
+
+    Ok(targets.into_iter()
+      .filter(|t| t.r#type == "page" && t.browser_context_id.as_ref() == Some(context))
+      .map(|t| t.target_id)
+      .collect())
+  }
+
+  /// the page for a target chrome opened on its own, such as a popup, once it is attached;
+  /// returns `None` while it is still being attached
+  pub async fn attach_page(&self, target: TargetId) -> Result<Option<Page>>{
+    let Ok(page) = self.browser().get_page(target).await else{
+      return Ok(None);
+    };
+    self.emulate_viewport(&page).await?;
+    Ok(Some(page))
+  }
+
+  /// yields whenever a target is created, attached or destroyed, so a change in a context's
+  /// tabs can be awaited instead of polled for
+  pub async fn target_changes(&self) -> Result<impl Stream<Item = ()> + Unpin + use<>>{
+    let browser = self.browser();
+    let created = browser.event_listener::<EventTargetCreated>().await?.map(|_| ());
+    let attached = browser.event_listener::<EventAttachedToTarget>().await?.map(|_| ());
+    let destroyed = browser.event_listener::<EventTargetDestroyed>().await?.map(|_| ());
+    Ok(stream::select(created, stream::select(attached, destroyed)))
+  }
+
+  pub async fn close_target(&self, target: TargetId) -> Result<()>{
+    self.browser().execute(CloseTargetParams::new(target))
+      .await
+      .context("failed to close target")?;
+    Ok(())
+  }
+
+  async fn emulate_viewport(&self, page: &Page) -> Result<()>{
     page.execute(
       SetDeviceMetricsOverrideParamsBuilder::default()
         .width(self.viewport_width)
@@ -71,41 +213,197 @@ impl BrowserController{
         .map_err(|e| anyhow::anyhow!("Failed to build device metrics: {}", e))?
     )
     .await?;
-
-    Ok(page)
+    Ok(())
   }
 
-  pub async fn set_cookies(&self, page: &Page, cookies: Vec<Cookie>) -> Result<()>{
-    for cookie in cookies{
-      let set_cookie_params = SetCookieParams::builder()
-        .name(cookie.name)
-        .value(cookie.value)
-        .domain(cookie.domain)
-        .build()
-        .map_err(|e| anyhow::anyhow!("Failed to build set cookie params: {}", e))?;
+  /// every cookie set in `context`, across all sites
+  pub async fn context_cookies(&self, context: &BrowserContextId) -> Result<Vec<Cookie>>{
+    let params = GetCookiesParams::builder()
+      .browser_context_id(context.clone())
+      .build();
+    let cookies = self.browser().execute(params)
+      .await
+      .context("failed to read cookies")?
+      .result
+      .cookies;
 
-      page.execute(set_cookie_params)
-        .await
-        .context("failed to set cookie")?;
+    Ok(cookies.into_iter().map(|cookie| Cookie{
+      name: cookie.name,
+      value: cookie.value,
+      domain: Some(cookie.domain),
+      url: None,
+      path: Some(cookie.path),
+      expires: (!cookie.session).then_some(cookie.expires),
+      http_only: cookie.http_only,
+      secure: cookie.secure,
+      same_site: cookie.same_site.map(|same_site| match same_site{
+        CookieSameSite::Strict => SameSite::Strict,
+        CookieSameSite::Lax => SameSite::Lax,
+        CookieSameSite::None => SameSite::None,
+      }),
+    }).collect())
+  }
+
+  /// sets cookies for every page of `context`, whichever site they belong to
+  pub async fn add_context_cookies(&self, context: &BrowserContextId, cookies: &[Cookie]) -> Result<()>{
+    if cookies.is_empty(){
+      return Ok(());
     }
+
+    let cookies = cookies.iter()
+      .map(|cookie|{
+        if cookie.domain.is_none() && cookie.url.is_none(){
+          anyhow::bail!("cookie {} needs a domain or a url", cookie.name);
+        }
+
+        let mut param = CookieParam::builder()
+          .name(cookie.name.clone())
+          .value(cookie.value.clone())
+          .http_only(cookie.http_only)
+          .secure(cookie.secure);
+        if let Some(domain) = &cookie.domain{
+          param = param.domain(domain.clone());
+        }
+        if let Some(url) = &cookie.url{
+          param = param.url(url.clone());
+        }
+        if let Some(path) = &cookie.path{
+          param = param.path(path.clone());
+        }
+        if let Some(expires) = cookie.expires{
+          param = param.expires(TimeSinceEpoch::new(expires));
+        }
+        if let Some(same_site) = cookie.same_site{
+          param = param.same_site(match same_site{
+            SameSite::Strict => CookieSameSite::Strict,
+            SameSite::Lax => CookieSameSite::Lax,
+            SameSite::None => CookieSameSite::None,
+          });
+        }
+        param.build().map_err(|e| anyhow::anyhow!("Failed to build cookie param: {}", e))
+      })
+      .collect::<Result<Vec<_>>>()?;
+
+    let mut params = SetCookiesParams::new(cookies);
+    params.browser_context_id = Some(context.clone());
+    self.browser().execute(params).await.context("failed to set cookies")?;
     Ok(())
   }
 
-  pub async fn set_local_storage(&self, page: &Page, items: HashMap<String, String>) -> Result<()>{
-    for (key, value) in items{
-      let script = format!(
-        "localStorage.setItem('{}', '{}');",
-        key.replace('\'', "\\'"),
-        value.replace('\'', "\\'")
-      );
-      page.evaluate(script).await?;
+  /// fills local and session storage and IndexedDB of the given origins in the first document
+  /// `page` loads from each of them, keeping any key or record the page has already set. an
+  /// origin is seeded once, so a key the app removes later, such as on logout, stays removed
+  pub async fn seed_storage(&self, page: &Page, origins: &[OriginStorage]) -> Result<()>{
+    if origins.is_empty(){
+      return Ok(());
     }
+
+    let mut navigated = page.event_listener::<EventFrameNavigated>().await?;
+    let mut pending = origins.to_vec();
+    let mut script = install_seed(page, &pending)
+      .await
+      .context("failed to install storage seed")?;
+    let page = page.clone();
+
+    // the seed runs as the document is created, so once a frame has navigated to an origin,
+    // that origin has been seeded and is dropped from the script for later documents
+    tokio::spawn(async move{
+      while let Some(event) = navigated.next().await{
+        let origin = &event.frame.security_origin;
+        if !pending.iter().any(|seed| seed.origin == *origin){
+          continue;
+        }
+        pending.retain(|seed| seed.origin != *origin);
+
+        let removed = page.execute(RemoveScriptToEvaluateOnNewDocumentParams::new(script.clone())).await;
+        if let Err(e) = removed{
+          eprintln!("failed to remove storage seed: {}", e);
+          return;
+        }
+        if pending.is_empty(){
+          return;
+        }
+        script = match install_seed(&page, &pending).await{
+          Ok(script) => script,
+          Err(e) => {
+            eprintln!("failed to reinstall storage seed: {:#}", e);
+            return;
+          }
+        };
+      }
+    });
     Ok(())
   }
 
-  pub async fn close(mut self) -> Result<()>{
-    self.browser.close().await?;
-    sleep(Duration::from_millis(500)).await;
+  /// local and session storage of the document loaded in `page`, if it has an origin
+  pub async fn read_storage(&self, page: &Page) -> Result<Option<OriginStorage>>{
+    let storage = page.evaluate(
+      r#"
+      (() => {
+        if(location.origin === 'null') return null;
+        try {
+          return { origin: location.origin, local_storage: {...localStorage}, session_storage: {...sessionStorage} };
+        } catch(e) {
+          return null;
+        }
+      })()
+      "#
+    ).await?;
+
+    match storage.value(){
+      Some(serde_json::Value::Null) | None => Ok(None),
+      Some(value) => Ok(Some(serde_json::from_value(value.clone())?)),
+    }
+  }
+
+  pub async fn close(self) -> Result<()>{
+    let launched = self.launched.into_inner().unwrap_or_else(|e| e.into_inner());
+    let Ok(mut launched) = Arc::try_unwrap(launched) else{
+      return Ok(());
+    };
+    if *launched.connected.borrow(){
+      launched.browser.close().await?;
+      sleep(Duration::from_millis(500)).await;
+    }
     Ok(())
   }
 }
+
+/// registers a script seeding `origins` in every document `page` creates from now on
+async fn install_seed(page: &Page, origins: &[OriginStorage]) -> Result<ScriptIdentifier>{
+  let script = format!(
+    r#"
+    (() => {{
+      const origins = {};
+      const seed = origins.find(o => o.origin === location.origin);
+      if(!seed) return;
+      for(const [storage, items] of [[localStorage, seed.local_storage], [sessionStorage, seed.session_storage]]){{
+        for(const [key, value] of Object.entries(items || {{}})){{
+          if(storage.getItem(key) === null) storage.setItem(key, value);
+        }}
+      }}
+      for(const db of seed.indexed_db || []){{
+        const request = indexedDB.open(db.name, db.version ?? 1);
+        request.onupgradeneeded = () => {{
+          const database = request.result;
+          for(const store of db.stores || []){{
+            const objects = database.objectStoreNames.contains(store.name)
+              ? request.transaction.objectStore(store.name)
+              : database.createObjectStore(store.name, {{
+                  keyPath: store.key_path ?? undefined,
+                  autoIncrement: !!store.auto_increment,
+                }});
+            for(const record of store.records || []){{
+              if(record.key === undefined || record.key === null) objects.put(record.value);
+              else objects.put(record.value, record.key);
+            }}
+          }}
+        }};
+        request.onsuccess = () => request.result.close();
+      }}
+    }})();
+    "#,
+    serde_json::to_string(origins)?
+  );
+  Ok(page.execute(AddScriptToEvaluateOnNewDocumentParams::new(script)).await?.result.identifier)
+}
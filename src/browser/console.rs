@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use anyhow::Result;
+use chromiumoxide::{
+  Page,
+  cdp::js_protocol::runtime::{EventConsoleApiCalled, EventExceptionThrown, RemoteObject, Timestamp},
+};
+use futures::{StreamExt, stream};
+use tokio::task::JoinHandle;
+use crate::models::console_message::ConsoleMessage;
+
+/// how many of a page's latest console messages are kept
+const KEPT: usize = 200;
+
+/// keeps the latest console messages and uncaught exceptions of a page, for failure reports
+pub struct ConsoleMonitor{
+  messages: Arc<Mutex<VecDeque<ConsoleMessage>>>,
+  task: JoinHandle<()>,
+}
+
+impl ConsoleMonitor{
+  pub async fn attach(page: &Page) -> Result<Self>{
+    let calls = page.event_listener::<EventConsoleApiCalled>().await?
+      .map(|e| ConsoleMessage{
+        level: e.r#type.as_ref().to_string(),
+        text: e.args.iter().map(describe).collect::<Vec<_>>().join(" "),
+        timestamp: timestamp(&e.timestamp),
+      });
+    let exceptions = page.event_listener::<EventExceptionThrown>().await?
+      .map(|e| {
+        let details = &e.exception_details;
+        let text = match details.exception.as_ref().and_then(|exception| exception.description.clone()){
+          Some(description) => description,
+          None => details.text.clone(),
+        };
+        ConsoleMessage{
+          level: "exception".to_string(),
+          text,
+          timestamp: timestamp(&e.timestamp),
+        }
+      });
+
+    let messages = Arc::new(Mutex::new(VecDeque::with_capacity(KEPT)));
+    let kept = messages.clone();
+    let task = tokio::spawn(async move{
+      let mut events = stream::select(calls, exceptions);
+      while let Some(message) = events.next().await{
+        let mut kept = kept.lock().unwrap();
+        if kept.len() == KEPT{
+          kept.pop_front();
+        }
+        kept.push_back(message);
+      }
+    });
+
+    Ok(Self{messages, task})
+  }
+
+  /// the kept messages, oldest first
+  pub fn recent(&self) -> Vec<ConsoleMessage>{
+    self.messages.lock().unwrap().iter().cloned().collect()
+  }
+}
+
+impl Drop for ConsoleMonitor{
+  fn drop(&mut self){
+    self.task.abort();
+  }
+}
+
+/// a console argument as devtools would print it: strings bare, other values by description
+fn describe(arg: &RemoteObject) -> String{
+  match (&arg.value, &arg.description){
+    (Some(serde_json::Value::String(value)), _) => value.clone(),
+    (_, Some(description)) => description.clone(),
+    (Some(value), None) => value.to_string(),
+    (None, None) => arg.r#type.as_ref().to_string(),
+  }
+}
+
+/// runtime timestamps are milliseconds since the epoch
+fn timestamp(timestamp: &Timestamp) -> String{
+  chrono::DateTime::from_timestamp_millis(*timestamp.inner() as i64)
+    .unwrap_or_else(chrono::Utc::now)
+    .to_rfc3339()
+}
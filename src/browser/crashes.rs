@@ -0,0 +1,33 @@
+use anyhow::Result;
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::inspector::{EnableParams, EventTargetCrashed},
+};
+use futures::StreamExt;
+use tokio::{sync::watch, task::JoinHandle};
+
+/// reports the tab's index once its renderer crashes; a crashed page answers nothing more
+pub struct CrashWatcher{
+  task: JoinHandle<()>,
+}
+
+impl CrashWatcher{
+  pub async fn attach(page: &Page, index: usize, crashed: watch::Sender<Option<usize>>) -> Result<Self>{
+    let mut events = page.event_listener::<EventTargetCrashed>().await?;
+    page.execute(EnableParams::default()).await?;
+
+    let task = tokio::spawn(async move{
+      if events.next().await.is_some(){
+        crashed.send_replace(Some(index));
+      }
+    });
+
+    Ok(Self{task})
+  }
+}
+
+impl Drop for CrashWatcher{
+  fn drop(&mut self){
+    self.task.abort();
+  }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+use anyhow::Result;
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::page::{DialogType, EventJavascriptDialogOpening, HandleJavaScriptDialogParams},
+};
+use futures::StreamExt;
+use tokio::{sync::{mpsc, watch}, task::JoinHandle, time::timeout};
+use crate::models::{
+  dialog_policy::{DialogAction, DialogPolicy},
+  dialog_record::DialogRecord,
+};
+use crate::state_capture::{CaptureOptions, capture_screenshot};
+
+/// how long a dialog is left open for a screenshot before it is answered regardless
+const SCREENSHOT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// a dialog a tab opened, and the page as it was while the dialog was open when the policy
+/// asked for a capture
+pub struct OpenedDialog{
+  pub record: DialogRecord,
+  pub tab: usize,
+  pub screenshot: Option<Vec<u8>>,
+}
+
+/// answers every dialog a page opens with the current policy, so none is left blocking it
+pub struct DialogHandler{
+  task: JoinHandle<()>,
+}
+
+impl DialogHandler{
+  pub async fn attach(
+    page: &Page,
+    index: usize,
+    policy: watch::Receiver<DialogPolicy>,
+    opened: mpsc::UnboundedSender<OpenedDialog>,
+  ) -> Result<Self>{
+    let mut events = page.event_listener::<EventJavascriptDialogOpening>().await?;
+    let page = page.clone();
+
+    let task = tokio::spawn(async move{
+      while let Some(event) = events.next().await{
+        let policy = policy.borrow().clone();
+        // the page behind the dialog still shows what it looked like when the dialog opened
+        let screenshot = if policy.capture{
+          match timeout(SCREENSHOT_TIMEOUT, capture_screenshot(&page, &CaptureOptions::default())).await{
+            Ok(Ok(screenshot)) => Some(screenshot),
+            Ok(Err(e)) => {
+              eprintln!("failed to capture page behind {} dialog: {:#}", event.r#type.as_ref(), e);
+              None
+            }
+            Err(_) => {
+              eprintln!("timed out capturing page behind {} dialog", event.r#type.as_ref());
+              None
+            }
+          }
+        }else{
+          None
+        };
+        let accepted = policy.action != DialogAction::Dismiss;
+        let prompt_text = match (policy.action, &event.r#type){
+          (DialogAction::Respond, DialogType::Prompt) => Some(policy.text.unwrap_or_default()),
+          _ => None,
+        };
+
+        let mut params = HandleJavaScriptDialogParams::new(accepted);
+        params.prompt_text = prompt_text.clone();
+        if let Err(e) = page.execute(params).await{
+          eprintln!("failed to answer {} dialog: {}", event.r#type.as_ref(), e);
+        }
+
+        let _ = opened.send(OpenedDialog{
+          record: DialogRecord{
+            kind: event.r#type.as_ref().to_string(),
+            message: event.message.clone(),
+            url: event.url.clone(),
+            accepted,
+            prompt_text,
+          },
+          tab: index,
+          screenshot,
+        });
+      }
+    });
+
+    Ok(Self{task})
+  }
+}
+
+impl Drop for DialogHandler{
+  fn drop(&mut self){
+    self.task.abort();
+  }
+}
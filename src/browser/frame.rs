@@ -0,0 +1,207 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::dom::{
+    BackendNodeId,
+    DescribeNodeParams,
+    GetBoxModelParams,
+    GetFrameOwnerParams,
+  },
+  cdp::browser_protocol::page::{EventFrameAttached, EventFrameNavigated, FrameId},
+  cdp::js_protocol::runtime::{EvaluateParams, EventExecutionContextCreated, ExecutionContextId},
+  error::CdpError,
+  js::EvaluationResult,
+};
+use tokio::time::sleep_until;
+use crate::browser::selector::ElementHandle;
+use crate::models::{frame_selector::FrameSelector, point::Point, task_error::TaskError};
+
+/// where a step's scripts run: the top document, or the document of one iframe
+#[derive(Debug, Clone)]
+pub struct FrameScope{
+  page: Page,
+  frame: Option<Arc<Frame>>,
+}
+
+/// the iframe a scope runs in, looked up again by its selector when its document is replaced
+#[derive(Debug)]
+struct Frame{
+  selector: FrameSelector,
+  attached: Mutex<Attached>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Attached{
+  context: ExecutionContextId,
+  owner: BackendNodeId,
+}
+
+impl FrameScope{
+  pub fn main(page: &Page) -> Self{
+    Self{page: page.clone(), frame: None}
+  }
+
+  /// finds the frame and waits for its document to have a javascript context, looking again
+  /// whenever a frame is attached or navigated or a context is created
+  pub async fn resolve(page: &Page, selector: &FrameSelector, timeout_ms: u64) -> Result<Self>{
+    let attached = locate(page, selector, None, timeout_ms).await?;
+    Ok(Self{
+      page: page.clone(),
+      frame: Some(Arc::new(Frame{
+        selector: selector.clone(),
+        attached: Mutex::new(attached),
+      })),
+    })
+  }
+
+  /// looks the frame up again after its document went away, waiting for a context other than
+  /// the lost one. the top document needs nothing, as chrome evaluates in whichever is current
+  pub async fn reattach(&self, timeout_ms: u64) -> Result<()>{
+    let Some(frame) = &self.frame else{
+      return Ok(());
+    };
+    let lost = frame.attached.lock().unwrap().context;
+    let attached = locate(&self.page, &frame.selector, Some(lost), timeout_ms).await?;
+    *frame.attached.lock().unwrap() = attached;
+    Ok(())
+  }
+
+  fn attached(&self) -> Option<Attached>{
+    self.frame.as_ref().map(|frame| *frame.attached.lock().unwrap())
+  }
+
+  pub fn page(&self) -> &Page{
+    &self.page
+  }
+
+  /// evaluates `script` in this frame, falling back to calling it when it is a function
+  pub async fn evaluate(&self, script: impl Into<String>) -> Result<EvaluationResult>{
+    let script = script.into();
+    let result = match self.attached(){
+      Some(attached) => {
+        let params = EvaluateParams::builder()
+          .expression(script)
+          .context_id(attached.context)
+          .eval_as_function_fallback(true)
+          .build()
+          .map_err(|e| anyhow::anyhow!("Failed to build evaluate params: {}", e))?;
+        self.page.evaluate(params).await?
+      }
+      None => self.page.evaluate(script).await?,
+    };
+    Ok(result)
+  }
+
+  pub fn evaluate_params(&self, expression: String) -> Result<EvaluateParams>{
+    let mut params = EvaluateParams::builder()
+      .expression(expression)
+      .return_by_value(false);
+    if let Some(attached) = self.attached(){
+      params = params.context_id(attached.context);
+    }
+    params.build().map_err(|e| anyhow::anyhow!("Failed to build evaluate params: {}", e))
+  }
+
+  /// converts a point in this frame's viewport to the top-level viewport input is sent to.
+  /// the owner is measured on every call since scrolling into view may move the frame
+  pub async fn to_viewport(&self, point: Point) -> Result<Point>{
+    let Some(attached) = self.attached() else{
+      return Ok(point);
+    };
+
+    let params = GetBoxModelParams::builder()
+      .backend_node_id(attached.owner)
+      .build();
+    let model = self.page.execute(params).await
+      .context("failed to measure the frame")?
+      .result
+      .model;
+    let content = model.content.inner();
+
+    Ok(Point{
+      x: content[0] + point.x,
+      y: content[1] + point.y,
+    })
+  }
+}
+
+/// waits for the frame `selector` picks to have a javascript context other than `lost`
+async fn locate(page: &Page, selector: &FrameSelector, lost: Option<ExecutionContextId>, timeout_ms: u64) -> Result<Attached>{
+  let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+  let mut attached = page.event_listener::<EventFrameAttached>().await?;
+  let mut navigated = page.event_listener::<EventFrameNavigated>().await?;
+  let mut contexts = page.event_listener::<EventExecutionContextCreated>().await?;
+
+  loop{
+    if let Some(frame_id) = find_frame(page, selector).await?
+      && let Some(context) = page.frame_execution_context(frame_id.clone()).await?
+      && Some(context) != lost
+    {
+      let params = GetFrameOwnerParams::new(frame_id);
+      let owner = page.execute(params).await
+        .with_context(|| format!("failed to find the element owning {}", selector))?;
+
+      return Ok(Attached{context, owner: owner.result.backend_node_id});
+    }
+
+    tokio::select!{
+      Some(_) = attached.next() => {}
+      Some(_) = navigated.next() => {}
+      Some(_) = contexts.next() => {}
+      _ = sleep_until(deadline.into()) => {
+        return Err(TaskError::timeout(format!("timeout after {}ms waiting for {}", timeout_ms, selector)).into());
+      }
+    }
+  }
+}
+
+async fn find_frame(page: &Page, selector: &FrameSelector) -> Result<Option<FrameId>>{
+  if let Some(css) = &selector.selector{
+    let Some(element) = ElementHandle::try_find(&FrameScope::main(page), css).await? else{
+      return Ok(None);
+    };
+    let params = DescribeNodeParams::builder()
+      .object_id(element.object_id().clone())
+      .build();
+    let node = page.execute(params).await.context("failed to describe frame element")?.result.node;
+    if node.node_name != "IFRAME" && node.node_name != "FRAME"{
+      anyhow::bail!("{} is not an iframe", css);
+    }
+    return Ok(node.frame_id);
+  }
+
+  if selector.name.is_none() && selector.url.is_none(){
+    anyhow::bail!("frame needs a selector, name or url");
+  }
+
+  for frame_id in page.frames().await?{
+    let matches = match (&selector.name, &selector.url){
+      (Some(name), _) => page.frame_name(frame_id.clone()).await?.as_deref() == Some(name.as_str()),
+      (None, Some(url)) => page.frame_url(frame_id.clone()).await?
+        .is_some_and(|frame_url| frame_url.contains(url.as_str())),
+      (None, None) => false,
+    };
+    if matches{
+      return Ok(Some(frame_id));
+    }
+  }
+  Ok(None)
+}
+
+/// whether `e` is chrome refusing to evaluate because the document it targeted is gone, as
+/// happens when a frame navigates, reloads or is detached mid-evaluation
+pub fn context_lost(e: &anyhow::Error) -> bool{
+  let Some(CdpError::Chrome(error)) = e.downcast_ref::<CdpError>() else{
+    return false;
+  };
+  [
+    "Cannot find context with specified id",
+    "Cannot find default execution context",
+    "Execution context was destroyed",
+    "Inspected target navigated or closed",
+    "Promise was collected",
+  ].iter().any(|message| error.message.contains(message))
+}
@@ -0,0 +1,234 @@
+use anyhow::{Context, Result};
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::input::{
+    DispatchKeyEventParams,
+    DispatchKeyEventParamsBuilder,
+    DispatchKeyEventType,
+    InsertTextParams,
+  },
+  keys::{KeyDefinition, get_key_definition},
+};
+
+const MODIFIER_ALT: i64 = 1;
+const MODIFIER_CONTROL: i64 = 2;
+const MODIFIER_META: i64 = 4;
+const MODIFIER_SHIFT: i64 = 8;
+
+/// characters of the US layout and what they turn into with shift held, letters aside
+const SHIFTED: [(&str, &str); 21] = [
+  ("`", "~"), ("1", "!"), ("2", "@"), ("3", "#"), ("4", "$"), ("5", "%"), ("6", "^"),
+  ("7", "&"), ("8", "*"), ("9", "("), ("0", ")"), ("-", "_"), ("=", "+"), ("[", "{"),
+  ("]", "}"), ("\\", "|"), (";", ":"), ("'", "\""), (",", "<"), (".", ">"), ("/", "?"),
+];
+
+/// a key combination such as `Control+Shift+P`, pressed as modifiers down, key down/up,
+/// modifiers up in reverse order
+#[derive(Debug)]
+pub struct KeyChord{
+  modifiers: Vec<&'static KeyDefinition>,
+  key: &'static KeyDefinition,
+}
+
+impl KeyChord{
+  pub fn parse(chord: &str) -> Result<Self>{
+    // a trailing `+` is the plus key itself, as in `Control++`
+    let (head, last) = if chord == "+"{
+      ("", "+")
+    }else if let Some(head) = chord.strip_suffix("++"){
+      (head, "+")
+    }else{
+      chord.rsplit_once('+').unwrap_or(("", chord))
+    };
+
+    let modifiers = head
+      .split('+')
+      .filter(|part| !part.is_empty())
+      .map(|part|{
+        let key = lookup(part)?;
+        if modifier_bit(key) == 0{
+          anyhow::bail!("'{}' is not a modifier key in chord '{}'", part, chord);
+        }
+        Ok(key)
+      })
+      .collect::<Result<Vec<_>>>()?;
+
+    let key = lookup(last).with_context(|| format!("invalid key chord: '{}'", chord))?;
+    Ok(Self{modifiers, key})
+  }
+
+  pub async fn press(&self, page: &Page) -> Result<()>{
+    let mut modifiers = 0;
+    for modifier in &self.modifiers{
+      modifiers |= modifier_bit(modifier);
+      dispatch(page, key_event(modifier, DispatchKeyEventType::RawKeyDown, modifiers)).await?;
+    }
+
+    let key = if modifiers & MODIFIER_SHIFT != 0{
+      shifted(self.key)
+    }else{
+      self.key
+    };
+    // with a command modifier held the key is a shortcut and must not insert text
+    let text = if modifiers & (MODIFIER_ALT | MODIFIER_CONTROL | MODIFIER_META) != 0{
+      None
+    }else{
+      key_text(key)
+    };
+    let down = match text{
+      Some(text) => key_event(key, DispatchKeyEventType::KeyDown, modifiers)
+        .text(text)
+        .unmodified_text(text),
+      None => key_event(key, DispatchKeyEventType::RawKeyDown, modifiers),
+    };
+    dispatch(page, down).await?;
+    dispatch(page, key_event(key, DispatchKeyEventType::KeyUp, modifiers)).await?;
+
+    for modifier in self.modifiers.iter().rev(){
+      modifiers &= !modifier_bit(modifier);
+      dispatch(page, key_event(modifier, DispatchKeyEventType::KeyUp, modifiers)).await?;
+    }
+    Ok(())
+  }
+}
+
+/// types `text` into the focused element one key at a time; characters with no key on the
+/// US layout are inserted directly
+pub async fn type_text(page: &Page, text: &str) -> Result<()>{
+  let mut buf = [0; 4];
+  for c in text.chars(){
+    let c = match c{
+      '\n' => "Enter",
+      '\t' => "Tab",
+      c => c.encode_utf8(&mut buf),
+    };
+    match get_key_definition(c){
+      Some(key) => KeyChord{modifiers: Vec::new(), key}.press(page).await?,
+      None => {
+        page.execute(InsertTextParams::new(c)).await.context("failed to insert text")?;
+      }
+    }
+  }
+  Ok(())
+}
+
+fn lookup(name: &str) -> Result<&'static KeyDefinition>{
+  let name = match name{
+    "Ctrl" => "Control",
+    "Cmd" | "Command" => "Meta",
+    "Option" => "Alt",
+    "Esc" => "Escape",
+    "Space" => " ",
+    "ControlOrMeta" if cfg!(target_os = "macos") => "Meta",
+    "ControlOrMeta" => "Control",
+    other => other,
+  };
+  get_key_definition(name).with_context(|| format!("unknown key: '{}'", name))
+}
+
+fn modifier_bit(key: &KeyDefinition) -> i64{
+  match key.key{
+    "Alt" => MODIFIER_ALT,
+    "Control" => MODIFIER_CONTROL,
+    "Meta" => MODIFIER_META,
+    "Shift" => MODIFIER_SHIFT,
+    _ => 0,
+  }
+}
+
+/// the key `key` turns into with shift held, such as `A` for `a` and `!` for `1`; keys shift
+/// does not change are returned as they are
+fn shifted(key: &'static KeyDefinition) -> &'static KeyDefinition{
+  let shifted = match SHIFTED.iter().find(|(unshifted, _)| *unshifted == key.key){
+    Some((_, shifted)) => get_key_definition(shifted),
+    None if key.key.len() == 1 && key.key.chars().all(|c| c.is_ascii_lowercase()) => {
+      get_key_definition(key.key.to_ascii_uppercase())
+    }
+    None => None,
+  };
+  shifted.unwrap_or(key)
+}
+
+fn key_text(key: &KeyDefinition) -> Option<&'static str>{
+  match key.text{
+    Some(text) => Some(text),
+    None if key.key.chars().count() == 1 => Some(key.key),
+    None => None,
+  }
+}
+
+fn key_event(key: &KeyDefinition, event_type: DispatchKeyEventType, modifiers: i64) -> DispatchKeyEventParamsBuilder{
+  DispatchKeyEventParams::builder()
+    .r#type(event_type)
+    .modifiers(modifiers)
+    .key(key.key)
+    .code(key.code)
+    .windows_virtual_key_code(key.key_code)
+    .native_virtual_key_code(key.key_code)
+}
+
+async fn dispatch(page: &Page, event: DispatchKeyEventParamsBuilder) -> Result<()>{
+  let params = event.build()
+    .map_err(|e| anyhow::anyhow!("Failed to build key event: {}", e))?;
+  page.execute(params).await.context("failed to dispatch key event")?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests{
+  use super::*;
+
+  fn keys(chord: &str) -> (Vec<&'static str>, &'static str){
+    let chord = KeyChord::parse(chord).unwrap();
+    (chord.modifiers.iter().map(|m| m.key).collect(), chord.key.key)
+  }
+
+  #[test]
+  fn chords_split_into_modifiers_and_a_key(){
+    assert_eq!(keys("Enter"), (vec![], "Enter"));
+    assert_eq!(keys("Control+Shift+p"), (vec!["Control", "Shift"], "p"));
+    assert_eq!(keys("Ctrl+Cmd+Option+Esc"), (vec!["Control", "Meta", "Alt"], "Escape"));
+    assert_eq!(keys("Shift+Space"), (vec!["Shift"], " "));
+  }
+
+  #[test]
+  fn a_trailing_plus_is_the_plus_key(){
+    assert_eq!(keys("+"), (vec![], "+"));
+    assert_eq!(keys("Control++"), (vec!["Control"], "+"));
+    assert_eq!(keys("Control+Shift++"), (vec!["Control", "Shift"], "+"));
+  }
+
+  #[test]
+  fn control_or_meta_follows_the_platform(){
+    let expected = if cfg!(target_os = "macos"){"Meta"}else{"Control"};
+    assert_eq!(keys("ControlOrMeta+a"), (vec![expected], "a"));
+  }
+
+  #[test]
+  fn invalid_chords_are_rejected(){
+    let error = format!("{:#}", KeyChord::parse("a+b").unwrap_err());
+    assert!(error.contains("'a' is not a modifier key in chord 'a+b'"), "{}", error);
+    let error = format!("{:#}", KeyChord::parse("Control+Nope").unwrap_err());
+    assert!(error.contains("invalid key chord: 'Control+Nope'"), "{}", error);
+    assert!(error.contains("unknown key: 'Nope'"), "{}", error);
+  }
+
+  #[test]
+  fn shift_turns_keys_into_their_us_layout_counterparts(){
+    let shift = |key: &str| shifted(get_key_definition(key).unwrap()).key;
+    assert_eq!(shift("a"), "A");
+    assert_eq!(shift("1"), "!");
+    assert_eq!(shift("/"), "?");
+    assert_eq!(shift("'"), "\"");
+    assert_eq!(shift("A"), "A");
+    assert_eq!(shift("Enter"), "Enter");
+  }
+
+  #[test]
+  fn only_printable_keys_have_text(){
+    assert_eq!(key_text(get_key_definition("a").unwrap()), Some("a"));
+    assert_eq!(key_text(get_key_definition("Enter").unwrap()), Some("\r"));
+    assert_eq!(key_text(get_key_definition("Tab").unwrap()), None);
+    assert_eq!(key_text(get_key_definition("ArrowLeft").unwrap()), None);
+  }
+}
@@ -1,2 +1,14 @@
+pub mod actionability;
 pub mod browser_constroller;
+pub mod console;
+pub mod crashes;
+pub mod dialogs;
+pub mod frame;
+pub mod keyboard;
+pub mod mouse;
+pub mod navigation;
+pub mod network_monitor;
 pub mod page_extension;
+pub mod selector;
+pub mod tabs;
+pub mod waiter;
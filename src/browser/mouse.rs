@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::input::{
+    DispatchMouseEventParams,
+    DispatchMouseEventType,
+    MouseButton as CdpMouseButton,
+  },
+};
+use crate::browser::actionability::{Actionability, Checks, wait_for_actionable};
+use crate::browser::frame::FrameScope;
+use crate::browser::selector::ElementHandle;
+use crate::models::{
+  mouse_button::MouseButton,
+  mouse_target::MouseTarget,
+  point::Point,
+};
+
+/// resolves a target to viewport coordinates, waiting for its element to pass `checks`
+/// unless the step forces the action. positions without a selector are always relative to
+/// the top-level viewport
+pub async fn resolve_target(scope: &FrameScope, target: &MouseTarget, checks: Checks, options: &Actionability) -> Result<Point>{
+  match (&target.selector, target.position){
+    (Some(selector), position) if !options.force => {
+      wait_for_actionable(scope, selector, position, checks, options).await
+    }
+    (Some(selector), position) => {
+      let element = ElementHandle::find(scope, selector).await?;
+      let rect = element.call(
+        "function() {
+          this.scrollIntoView({ block: 'center', inline: 'center', behavior: 'instant' });
+          const r = this.getBoundingClientRect();
+          return { x: r.left, y: r.top, width: r.width, height: r.height };
+        }"
+      ).await?;
+      let (x, y) = (rect["x"].as_f64().unwrap_or_default(), rect["y"].as_f64().unwrap_or_default());
+      let (width, height) = (rect["width"].as_f64().unwrap_or_default(), rect["height"].as_f64().unwrap_or_default());
+
+      scope.to_viewport(match position{
+        Some(offset) => Point{x: x + offset.x, y: y + offset.y},
+        None => Point{x: x + width / 2.0, y: y + height / 2.0},
+      }).await
+    }
+    (None, Some(position)) => Ok(position),
+    (None, None) => anyhow::bail!("mouse action needs a selector or a position"),
+  }
+}
+
+pub async fn move_to(page: &Page, point: Point, buttons: i64) -> Result<()>{
+  dispatch(page, DispatchMouseEventType::MouseMoved, point, None, buttons, 0).await
+}
+
+pub async fn down(page: &Page, point: Point, button: MouseButton, click_count: i64) -> Result<()>{
+  dispatch(page, DispatchMouseEventType::MousePressed, point, Some(button), button_mask(button), click_count).await
+}
+
+pub async fn up(page: &Page, point: Point, button: MouseButton, click_count: i64) -> Result<()>{
+  dispatch(page, DispatchMouseEventType::MouseReleased, point, Some(button), 0, click_count).await
+}
+
+/// moves to `point` and presses `button` `click_count` times, reporting the running click
+/// count on each press the way a real double or triple click does
+pub async fn click(page: &Page, point: Point, button: MouseButton, click_count: u32) -> Result<()>{
+  move_to(page, point, 0).await?;
+  for count in 1..=click_count.max(1) as i64{
+    down(page, point, button, count).await?;
+    up(page, point, button, count).await?;
+  }
+  Ok(())
+}
+
+/// presses at `from`, moves to `to` in `steps` intermediate moves and releases there
+pub async fn drag(page: &Page, from: Point, to: Point, steps: u32) -> Result<()>{
+  let steps = steps.max(1);
+  move_to(page, from, 0).await?;
+  down(page, from, MouseButton::Left, 1).await?;
+
+  for step in 1..=steps{
+    let t = step as f64 / steps as f64;
+    let point = Point{
+      x: from.x + (to.x - from.x) * t,
+      y: from.y + (to.y - from.y) * t,
+    };
+    move_to(page, point, button_mask(MouseButton::Left)).await?;
+  }
+
+  up(page, to, MouseButton::Left, 1).await
+}
+
+pub fn button_mask(button: MouseButton) -> i64{
+  match button{
+    MouseButton::Left => 1,
+    MouseButton::Right => 2,
+    MouseButton::Middle => 4,
+  }
+}
+
+async fn dispatch(
+  page: &Page,
+  event_type: DispatchMouseEventType,
+  point: Point,
+  button: Option<MouseButton>,
+  buttons: i64,
+  click_count: i64,
+) -> Result<()>{
+  let button = match button{
+    Some(MouseButton::Left) => CdpMouseButton::Left,
+    Some(MouseButton::Middle) => CdpMouseButton::Middle,
+    Some(MouseButton::Right) => CdpMouseButton::Right,
+    None => CdpMouseButton::None,
+  };
+
+  let params = DispatchMouseEventParams::builder()
+    .r#type(event_type)
+    .x(point.x)
+    .y(point.y)
+    .button(button)
+    .buttons(buttons)
+    .click_count(click_count)
+    .build()
+    .map_err(|e| anyhow::anyhow!("Failed to build mouse event: {}", e))?;
+
+  page.execute(params).await.context("failed to dispatch mouse event")?;
+  Ok(())
+}
@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Context, Result};
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::{
+    network::{EventRequestWillBeSent, EventResponseReceived, LoaderId, ResourceType},
+    page::{EventLifecycleEvent, FrameId, NavigateParams},
+  },
+};
+use futures::{StreamExt, stream};
+use tokio::{sync::watch, task::JoinHandle, time::timeout};
+use crate::models::navigation::{NavigationOptions, Redirect, WaitUntil};
+use crate::models::task_error::TaskError;
+
+enum DocumentEvent{
+  Request(Arc<EventRequestWillBeSent>),
+  Response(Arc<EventResponseReceived>),
+}
+
+/// the document last loaded into a page's top frame: the url it ended up at, the status it
+/// was served with, and the redirects on the way there
+#[derive(Debug, Clone, Default)]
+pub struct DocumentResponse{
+  loader_id: Option<LoaderId>,
+  pub url: String,
+  pub status: Option<i64>,
+  pub redirects: Vec<Redirect>,
+}
+
+/// follows the document requests of a page's top frame from the moment it is attached until
+/// the page closes
+pub struct NavigationMonitor{
+  document: watch::Receiver<DocumentResponse>,
+  task: JoinHandle<()>,
+}
+
+impl NavigationMonitor{
+  pub async fn attach(page: &Page) -> Result<Self>{
+    let main_frame = page.mainframe().await?;
+    let requests = page.event_listener::<EventRequestWillBeSent>().await?
+      .map(DocumentEvent::Request);
+    let responses = page.event_listener::<EventResponseReceived>().await?
+      .map(DocumentEvent::Response);
+    let (tx, rx) = watch::channel(DocumentResponse::default());
+
+    let task = tokio::spawn(async move{
+      let mut events = stream::select(requests, responses);
+      let in_top_frame = |frame_id: Option<&FrameId>| main_frame.is_none() || main_frame.as_ref() == frame_id;
+
+      while let Some(event) = events.next().await{
+        match event{
+          // a navigation request keeps the id of its loader across redirects
+          DocumentEvent::Request(e) => {
+            if e.r#type != Some(ResourceType::Document)
+              || !in_top_frame(e.frame_id.as_ref())
+              || e.request_id.inner() != e.loader_id.inner()
+            {
+              continue;
+            }
+            tx.send_modify(|document|{
+              if document.loader_id.as_ref() != Some(&e.loader_id){
+                *document = DocumentResponse{loader_id: Some(e.loader_id.clone()), ..Default::default()};
+              }
+              if let Some(redirect) = &e.redirect_response{
+                document.redirects.push(Redirect{url: redirect.url.clone(), status: redirect.status});
+              }
+              document.url = e.request.url.clone();
+            });
+          }
+          DocumentEvent::Response(e) => {
+            if e.r#type != ResourceType::Document || !in_top_frame(e.frame_id.as_ref()){
+              continue;
+            }
+            tx.send_if_modified(|document|{
+              if document.loader_id.as_ref() != Some(&e.loader_id){
+                return false;
+              }
+              document.url = e.response.url.clone();
+              document.status = Some(e.response.status);
+              true
+            });
+          }
+        }
+      }
+    });
+
+    Ok(Self{document: rx, task})
+  }
+
+  pub fn document(&self) -> DocumentResponse{
+    self.document.borrow().clone()
+  }
+
+  /// navigates the top frame of `page` to `url` and waits for it as `options` say. fails on
+  /// network errors, on the timeout, and when the final response has a status in
+  /// `fail_on_status`
+  pub async fn navigate(&self, page: &Page, url: &str, options: &NavigationOptions) -> Result<DocumentResponse>{
+    let document = timeout(
+      Duration::from_millis(options.timeout_ms),
+      self.navigate_and_wait(page, url, options.wait_until),
+    ).await
+      .map_err(|_| TaskError::timeout(format!(
+        "timeout after {}ms navigating to {} (waiting for {})",
+        options.timeout_ms, url, options.wait_until
+      )).with_url(url))??;
+
+    if let Some(status) = document.status
+      && options.fail_on_status.iter().any(|range| range.contains(status))
+    {
+      let redirected = if document.redirects.is_empty(){
+        String::new()
+      }else{
+        format!(" after {} redirects", document.redirects.len())
+      };
+      let message = format!(
+        "navigation to {} ended at {}{} with status {}",
+        url, document.url, redirected, status
+      );
+      return Err(TaskError::navigation_failed(&document.url, message).into());
+    }
+    Ok(document)
+  }
+
+  async fn navigate_and_wait(&self, page: &Page, url: &str, wait_until: WaitUntil) -> Result<DocumentResponse>{
+    let mut lifecycle = page.event_listener::<EventLifecycleEvent>().await?;
+    let navigated = page.execute(NavigateParams::new(url))
+      .await
+      .with_context(|| format!("failed to navigate to {}", url))?
+      .result;
+    if let Some(error) = navigated.error_text{
+      let message = format!("failed to navigate to {}: {}", url, error);
+      return Err(TaskError::navigation_failed(url, message).into());
+    }
+    // a navigation within the document, such as to another #fragment, loads nothing
+    let Some(loader_id) = navigated.loader_id else{
+      return Ok(self.document());
+    };
+
+    let event = match wait_until{
+      WaitUntil::Commit => None,
+      WaitUntil::DomContentLoaded => Some("DOMContentLoaded"),
+      WaitUntil::Load => Some("load"),
+      WaitUntil::NetworkIdle => Some("networkIdle"),
+    };
+    if let Some(event) = event{
+      loop{
+        let lifecycle = lifecycle.next().await.context("page closed while navigating")?;
+        if lifecycle.loader_id == loader_id && lifecycle.frame_id == navigated.frame_id && lifecycle.name == event{
+          break;
+        }
+      }
+    }
+
+    // only http responses have a status to wait for
+    if !url.starts_with("http"){
+      return Ok(self.document());
+    }
+    let mut document = self.document.clone();
+    let document = document
+      .wait_for(|document| document.loader_id.as_ref() == Some(&loader_id) && document.status.is_some())
+      .await
+      .context("page closed while navigating")?
+      .clone();
+    Ok(document)
+  }
+}
+
+impl Drop for NavigationMonitor{
+  fn drop(&mut self){
+    self.task.abort();
+  }
+}
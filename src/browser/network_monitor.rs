@@ -0,0 +1,196 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::network::{
+    EventLoadingFailed,
+    EventLoadingFinished,
+    EventRequestWillBeSent,
+    EventResponseReceived,
+    RequestId,
+  },
+};
+use futures::{StreamExt, stream};
+use tokio::{sync::watch, task::JoinHandle};
+use crate::models::{network_request::NetworkRequest, task_error::TaskError};
+
+/// how many of a page's latest requests are kept
+const KEPT: usize = 100;
+
+enum NetworkEvent{
+  /// a request was sent, or redirected with the status it was redirected with
+  Started(RequestId, NetworkRequest, Option<i64>),
+  Responded(RequestId, i64),
+  Finished(RequestId),
+  Failed(RequestId, String),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Activity{
+  in_flight: usize,
+  last_change: Instant,
+}
+
+/// tracks in-flight requests of a page from the moment it is attached until the page closes,
+/// and keeps the latest ones for failure reports
+pub struct NetworkMonitor{
+  activity: watch::Receiver<Activity>,
+  requests: Arc<Mutex<VecDeque<(RequestId, NetworkRequest)>>>,
+  task: JoinHandle<()>,
+}
+
+impl NetworkMonitor{
+  pub async fn attach(page: &Page) -> Result<Self>{
+    let started = page.event_listener::<EventRequestWillBeSent>().await?
+      .map(|e| NetworkEvent::Started(
+        e.request_id.clone(),
+        NetworkRequest{
+          method: e.request.method.clone(),
+          url: e.request.url.clone(),
+          resource_type: e.r#type.as_ref().map(|kind| kind.as_ref().to_string()),
+          status: None,
+          error: None,
+          finished: false,
+        },
+        e.redirect_response.as_ref().map(|response| response.status),
+      ));
+    let responded = page.event_listener::<EventResponseReceived>().await?
+      .map(|e| NetworkEvent::Responded(e.request_id.clone(), e.response.status));
+    let finished = page.event_listener::<EventLoadingFinished>().await?
+      .map(|e| NetworkEvent::Finished(e.request_id.clone()));
+    let failed = page.event_listener::<EventLoadingFailed>().await?
+      .map(|e| NetworkEvent::Failed(e.request_id.clone(), e.error_text.clone()));
+
+    let (tx, rx) = watch::channel(Activity{
+      in_flight: 0,
+      last_change: Instant::now(),
+    });
+
+    let requests = Arc::new(Mutex::new(VecDeque::with_capacity(KEPT)));
+    let kept = requests.clone();
+
+    let task = tokio::spawn(async move{
+      let mut events = stream::select(
+        stream::select(started, responded),
+        stream::select(finished, failed),
+      );
+      let mut in_flight = HashSet::new();
+
+      while let Some(event) = events.next().await{
+        let mut kept = kept.lock().unwrap();
+
+        let changed = match event{
+          NetworkEvent::Started(id, request, redirected_with) => {
+            if let Some(status) = redirected_with
+              && let Some(redirected) = latest(&mut kept, &id)
+            {
+              redirected.status = Some(status);
+              redirected.finished = true;
+            }
+            if kept.len() == KEPT{
+              kept.pop_front();
+            }
+            kept.push_back((id.clone(), request));
+            in_flight.insert(id)
+          }
+          NetworkEvent::Responded(id, status) => {
+            if let Some(request) = latest(&mut kept, &id){
+              request.status = Some(status);
+            }
+            false
+          }
+          NetworkEvent::Finished(id) => {
+            if let Some(request) = latest(&mut kept, &id){
+              request.finished = true;
+            }
+            in_flight.remove(&id)
+          }
+          NetworkEvent::Failed(id, error) => {
+            if let Some(request) = latest(&mut kept, &id){
+              request.error = Some(error);
+              request.finished = true;
+            }
+            in_flight.remove(&id)
+          }
+        };
+        drop(kept);
+
+        if changed{
+          tx.send_replace(Activity{
+            in_flight: in_flight.len(),
+            last_change: Instant::now(),
+          });
+        }
+      }
+    });
+
+    Ok(Self{activity: rx, requests, task})
+  }
+
+  /// the kept requests, oldest first
+  pub fn recent_requests(&self) -> Vec<NetworkRequest>{
+    self.requests.lock().unwrap().iter().map(|(_, request)| request.clone()).collect()
+  }
+
+  /// resolves once no more than `max_connections` requests have been outstanding for `idle_ms`.
+  /// requests starting and finishing within that limit do not restart the quiet window, so a
+  /// page that keeps a poll or beacons going still counts as idle
+  pub async fn wait_for_idle(&self, max_connections: usize, idle_ms: u64, timeout_ms: u64) -> Result<()>{
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let idle = Duration::from_millis(idle_ms);
+    let mut activity = self.activity.clone();
+    // when the count last came down to the limit; the count has not changed since
+    // `last_change`, so that is the latest it can have come down
+    let mut quiet_since: Option<Instant> = None;
+
+    loop{
+      let current = *activity.borrow_and_update();
+      quiet_since = match (current.in_flight <= max_connections, quiet_since){
+        (false, _) => None,
+        (true, None) => Some(current.last_change),
+        (true, since) => since,
+      };
+      let now = Instant::now();
+
+      if let Some(since) = quiet_since
+        && now >= since + idle
+      {
+        return Ok(());
+      }
+      if now >= deadline{
+        return Err(TaskError::timeout(format!(
+          "timeout after {}ms waiting for network idle: {} requests in flight (max {})",
+          timeout_ms, current.in_flight, max_connections
+        )).into());
+      }
+
+      let wake = match quiet_since{
+        Some(since) => (since + idle).min(deadline),
+        None => deadline,
+      };
+
+      tokio::select!{
+        changed = activity.changed() => {
+          if changed.is_err(){
+            anyhow::bail!("page closed while waiting for network idle");
+          }
+        }
+        _ = tokio::time::sleep_until(wake.into()) => {}
+      }
+    }
+  }
+}
+
+/// the kept request with `id`. a redirect reuses the request id, so the latest entry with it is
+/// the live one
+fn latest<'a>(kept: &'a mut VecDeque<(RequestId, NetworkRequest)>, id: &RequestId) -> Option<&'a mut NetworkRequest>{
+  kept.iter_mut().rev().find(|(kept_id, _)| kept_id == id).map(|(_, request)| request)
+}
+
+impl Drop for NetworkMonitor{
+  fn drop(&mut self){
+    self.task.abort();
+  }
+}
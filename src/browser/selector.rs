@@ -0,0 +1,339 @@
+use anyhow::{Context, Result};
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::dom::SetFileInputFilesParams,
+  cdp::js_protocol::runtime::{
+    CallFunctionOnParams,
+    RemoteObjectId,
+    RemoteObjectSubtype,
+  },
+};
+use crate::browser::frame::FrameScope;
+use crate::models::task_error::{ErrorKind, TaskError};
+
+/// in-page selector engine, installed once per document. a selector is one or more parts joined
+/// by `>>`, each part searching inside the matches of the previous one. parts are prefixed with
+/// the engine to use: `css=`, `text=`, `role=`, `label=`, `xpath=` or `testid=`; unprefixed parts
+/// are css, or xpath when they start with `//`. quoted `text=`/`label=` values match exactly,
+/// unquoted ones match a case-insensitive substring. every engine but xpath also searches inside
+/// open shadow roots; closed ones are unreachable from page scripts
+const ENGINE: &str = r#"(window.__softlightSelectors || (window.__softlightSelectors = (() => {
+  const normalize = (s) => (s || '').replace(/\s+/g, ' ').trim();
+  const unquote = (s) => {
+    const m = s.match(/^"((?:[^"\\]|\\.)*)"$/s) || s.match(/^'([^']*)'$/s);
+    return m ? { value: m[1].replace(/\\(.)/g, '$1'), quoted: true } : { value: s.trim(), quoted: false };
+  };
+  const matcher = (body) => {
+    const { value, quoted } = unquote(body.trim());
+    const expected = quoted ? normalize(value) : normalize(value).toLowerCase();
+    return (text) => quoted
+      ? normalize(text) === expected
+      : normalize(text).toLowerCase().includes(expected);
+  };
+  // the root itself plus every open shadow root below it, in tree order
+  const scopes = (root) => {
+    const out = root.shadowRoot ? [root, root.shadowRoot] : [root];
+    for(let i = 0; i < out.length; i++){
+      for(const el of out[i].querySelectorAll('*')){
+        if(el.shadowRoot) out.push(el.shadowRoot);
+      }
+    }
+    return out;
+  };
+  const selectAll = (root, css) => scopes(root).flatMap(scope => Array.from(scope.querySelectorAll(css)));
+  const descendants = (root) => selectAll(root, '*');
+  const skipped = new Set(['SCRIPT', 'STYLE', 'NOSCRIPT', 'TEMPLATE', 'HEAD', 'TITLE']);
+
+  const implicitRole = (el) => {
+    const tag = el.tagName.toLowerCase();
+    if(/^h[1-6]$/.test(tag)) return 'heading';
+    switch(tag){
+      case 'a': case 'area': return el.hasAttribute('href') ? 'link' : null;
+      case 'button': return 'button';
+      case 'input': {
+        const type = (el.getAttribute('type') || 'text').toLowerCase();
+        return ({
+          button: 'button', submit: 'button', reset: 'button', image: 'button',
+          checkbox: 'checkbox', radio: 'radio', range: 'slider', number: 'spinbutton',
+          search: 'searchbox', text: 'textbox', email: 'textbox', tel: 'textbox',
+          url: 'textbox', password: 'textbox',
+        })[type] || null;
+      }
+      case 'textarea': return 'textbox';
+      case 'select': return el.multiple || el.size > 1 ? 'listbox' : 'combobox';
+      case 'option': return 'option';
+      case 'img': return el.getAttribute('alt') === '' ? 'presentation' : 'img';
+      case 'ul': case 'ol': return 'list';
+      case 'li': return 'listitem';
+      case 'nav': return 'navigation';
+      case 'main': return 'main';
+      case 'header': return 'banner';
+      case 'footer': return 'contentinfo';
+      case 'aside': return 'complementary';
+      case 'form': return 'form';
+      case 'dialog': return 'dialog';
+      case 'table': return 'table';
+      case 'tr': return 'row';
+      case 'td': return 'cell';
+      case 'th': return 'columnheader';
+      default: return null;
+    }
+  };
+  const roleOf = (el) => (el.getAttribute('role') || '').trim().split(/\s+/)[0] || implicitRole(el);
+  const textOfIds = (el, ids) => ids.split(/\s+/)
+    .map(id => el.getRootNode().getElementById?.(id)?.textContent || '')
+    .join(' ');
+  const accessibleName = (el) => {
+    if(el.hasAttribute('aria-labelledby')) return normalize(textOfIds(el, el.getAttribute('aria-labelledby')));
+    if(el.hasAttribute('aria-label')) return normalize(el.getAttribute('aria-label'));
+    if(el.labels && el.labels.length) return normalize(Array.from(el.labels).map(l => l.textContent).join(' '));
+    if(el.tagName === 'IMG' || el.tagName === 'AREA') return normalize(el.getAttribute('alt'));
+    if(el.tagName === 'INPUT' && ['button', 'submit', 'reset'].includes(el.type)) return normalize(el.value);
+    return normalize(el.textContent) || normalize(el.getAttribute('title') || el.getAttribute('placeholder'));
+  };
+  const ariaState = (el, name) => {
+    switch(name){
+      case 'checked': return el.checked ?? el.getAttribute('aria-checked') === 'true';
+      case 'disabled': return !!el.disabled || el.getAttribute('aria-disabled') === 'true';
+      case 'selected': return el.selected ?? el.getAttribute('aria-selected') === 'true';
+      case 'expanded': return el.getAttribute('aria-expanded') === 'true';
+      case 'pressed': return el.getAttribute('aria-pressed') === 'true';
+      case 'level': return Number(el.getAttribute('aria-level') || el.tagName.slice(1));
+      default: throw new Error('unsupported role attribute: ' + name);
+    }
+  };
+
+  const engines = {
+    css: (root, body) => selectAll(root, body),
+    xpath: (root, body) => {
+      const result = document.evaluate(body, root, null, XPathResult.ORDERED_NODE_SNAPSHOT_TYPE, null);
+      const out = [];
+      for(let i = 0; i < result.snapshotLength; i++){
+        const node = result.snapshotItem(i);
+        if(node.nodeType === Node.ELEMENT_NODE) out.push(node);
+      }
+      return out;
+    },
+    text: (root, body) => {
+      const matches = matcher(body);
+      const found = new Set(descendants(root).filter(el => !skipped.has(el.tagName) && matches(el.textContent)));
+      // keep the innermost matches so `text=Issues` lands on the tab, not the whole nav
+      return Array.from(found).filter(el => !Array.from(el.children).some(child => found.has(child)));
+    },
+    testid: (root, body) => {
+      const { value } = unquote(body);
+      return selectAll(root, '[data-testid="' + CSS.escape(value) + '"]');
+    },
+    label: (root, body) => {
+      const matches = matcher(body);
+      const found = new Set();
+      for(const label of selectAll(root, 'label')){
+        if(label.control && matches(label.textContent)) found.add(label.control);
+      }
+      for(const el of selectAll(root, '[aria-label]')){
+        if(matches(el.getAttribute('aria-label'))) found.add(el);
+      }
+      for(const el of selectAll(root, '[aria-labelledby]')){
+        if(matches(textOfIds(el, el.getAttribute('aria-labelledby')))) found.add(el);
+      }
+      return Array.from(found);
+    },
+    role: (root, body) => {
+      const m = body.trim().match(/^([\w-]+)\s*((?:\[[^\]]*\]\s*)*)$/);
+      if(!m) throw new Error('invalid role selector: ' + body);
+      const filters = Array.from(m[2].matchAll(/\[\s*([\w-]+)\s*(?:=\s*("(?:[^"\\]|\\.)*"|'[^']*'|[^\]]*?))?\s*\]/g))
+        .map(([, name, value]) => {
+          if(name === 'name') {
+            const matches = matcher(value ?? '');
+            return (el) => matches(accessibleName(el));
+          }
+          const expected = value === undefined ? true : JSON.parse(unquote(value).value);
+          return (el) => ariaState(el, name) === expected;
+        });
+      return descendants(root).filter(el => roleOf(el) === m[1] && filters.every(f => f(el)));
+    },
+  };
+
+  const splitChain = (selector) => {
+    const parts = [];
+    let quote = null, depth = 0, start = 0;
+    for(let i = 0; i < selector.length; i++){
+      const c = selector[i];
+      if(quote){ if(c === '\\') i++; else if(c === quote) quote = null; continue; }
+      if(c === '"' || c === "'") quote = c;
+      else if(c === '[' || c === '(') depth++;
+      else if(c === ']' || c === ')') depth--;
+      else if(depth === 0 && c === '>' && selector[i + 1] === '>'){
+        parts.push(selector.slice(start, i));
+        start = i + 2;
+        i++;
+      }
+    }
+    parts.push(selector.slice(start));
+    return parts.map(p => p.trim());
+  };
+  const parsePart = (part) => {
+    const m = part.match(/^(css|text|role|label|xpath|testid)\s*=\s*(.*)$/s);
+    if(m) return { engine: m[1], body: m[2] };
+    if(/^\(*\.{0,2}\//.test(part)) return { engine: 'xpath', body: part };
+    return { engine: 'css', body: part };
+  };
+
+  const queryAll = (selector, scope) => {
+    let roots = [scope || document];
+    for(const part of splitChain(selector)){
+      if(!part) throw new Error('empty selector part in: ' + selector);
+      const { engine, body } = parsePart(part);
+      const found = new Set();
+      for(const root of roots){
+        for(const el of engines[engine](root, body)) found.add(el);
+      }
+      roots = Array.from(found).sort((a, b) =>
+        a === b ? 0 : a.compareDocumentPosition(b) & Node.DOCUMENT_POSITION_FOLLOWING ? -1 : 1);
+    }
+    return roots;
+  };
+
+  return {
+    queryAll,
+    query: (selector, scope) => queryAll(selector, scope)[0] || null,
+  };
+})()))"#;
+
+/// javascript expression evaluating to the first element matching `selector`, or null
+pub fn query_expression(selector: &str) -> String{
+  format!("{}.query({})", ENGINE, js_string(selector))
+}
+
+/// javascript expression evaluating to an array of every element matching `selector`
+pub fn query_all_expression(selector: &str) -> String{
+  format!("{}.queryAll({})", ENGINE, js_string(selector))
+}
+
+pub fn js_string(value: &str) -> String{
+  serde_json::Value::from(value).to_string()
+}
+
+/// a reference to a live element in the page, found through the selector engine
+pub struct ElementHandle{
+  page: Page,
+  object_id: RemoteObjectId,
+}
+
+impl ElementHandle{
+  pub async fn find(scope: &FrameScope, selector: &str) -> Result<Self>{
+    Self::try_find(scope, selector).await?
+      .ok_or_else(|| TaskError::selector_not_found(selector).into())
+  }
+
+  pub async fn try_find(scope: &FrameScope, selector: &str) -> Result<Option<Self>>{
+    let params = scope.evaluate_params(query_expression(selector))?;
+
+    let result = scope.page().evaluate_expression(params).await
+      .with_context(|| format!("invalid selector: {}", selector))?;
+    let object = result.object();
+    if object.subtype == Some(RemoteObjectSubtype::Null){
+      return Ok(None);
+    }
+
+    Ok(object.object_id.clone().map(|object_id| Self{
+      page: scope.page().clone(),
+      object_id,
+    }))
+  }
+
+  pub fn object_id(&self) -> &RemoteObjectId{
+    &self.object_id
+  }
+
+  /// calls `function` with the element bound to `this`, awaiting a returned promise
+  pub async fn call(&self, function: &str) -> Result<serde_json::Value>{
+    let params = CallFunctionOnParams::builder()
+      .function_declaration(function)
+      .object_id(self.object_id.clone())
+      .return_by_value(true)
+      .await_promise(true)
+      .build()
+      .map_err(|e| anyhow::anyhow!("Failed to build call function params: {}", e))?;
+
+    let result = self.page.execute(params).await?.result;
+    if let Some(exception) = result.exception_details{
+      let message = exception.exception
+        .and_then(|e| e.description)
+        .unwrap_or(exception.text);
+      return Err(TaskError::new(ErrorKind::ScriptError, format!("script error: {}", message)).into());
+    }
+    Ok(result.result.value.unwrap_or_default())
+  }
+
+  pub async fn text(&self) -> Result<String>{
+    let value = self.call("function() { return this.innerText ?? this.textContent; }").await?;
+    Ok(value.as_str().unwrap_or_default().to_string())
+  }
+
+  pub async fn attribute(&self, name: &str) -> Result<Option<String>>{
+    let value = self.call(&format!("function() {{ return this.getAttribute({}); }}", js_string(name))).await?;
+    Ok(value.as_str().map(String::from))
+  }
+
+  pub async fn focus(&self) -> Result<()>{
+    self.call("function() { this.focus(); }").await?;
+    Ok(())
+  }
+
+  pub async fn is_checked(&self) -> Result<bool>{
+    let value = self.call(
+      "function() { return this.checked ?? this.getAttribute('aria-checked') === 'true'; }"
+    ).await?;
+    Ok(value.as_bool().unwrap_or(false))
+  }
+
+  /// selects every option matching one of the given values, labels or indexes and fires
+  /// `input`/`change`; returns the values that ended up selected
+  pub async fn select_options(&self, values: &[String], labels: &[String], indexes: &[usize]) -> Result<Vec<String>>{
+    let script = format!(
+      r#"
+      function() {{
+        if(this.tagName !== 'SELECT') return {{ error: 'element is not a <select>' }};
+        const values = {};
+        const labels = {};
+        const indexes = {};
+        const options = Array.from(this.options);
+        const matched = options.filter((o, i) =>
+          values.includes(o.value) || labels.includes(o.label.trim()) || indexes.includes(i));
+        const missing = [
+          ...values.filter(v => !options.some(o => o.value === v)),
+          ...labels.filter(l => !options.some(o => o.label.trim() === l)),
+          ...indexes.filter(i => i >= options.length).map(String),
+        ];
+        if(missing.length) return {{ error: 'no option matching: ' + missing.join(', ') }};
+        if(!this.multiple && matched.length > 1) return {{ error: 'multiple options match a single-select' }};
+        options.forEach(o => {{ o.selected = matched.includes(o); }});
+        this.dispatchEvent(new Event('input', {{ bubbles: true }}));
+        this.dispatchEvent(new Event('change', {{ bubbles: true }}));
+        return {{ selected: Array.from(this.selectedOptions).map(o => o.value) }};
+      }}
+      "#,
+      serde_json::to_string(values)?,
+      serde_json::to_string(labels)?,
+      serde_json::to_string(indexes)?,
+    );
+
+    let result = self.call(&script).await?;
+    if let Some(error) = result["error"].as_str(){
+      anyhow::bail!("{}", error);
+    }
+    Ok(serde_json::from_value(result["selected"].clone())?)
+  }
+
+  pub async fn set_input_files(&self, files: Vec<String>) -> Result<()>{
+    let params = SetFileInputFilesParams::builder()
+      .files(files)
+      .object_id(self.object_id.clone())
+      .build()
+      .map_err(|e| anyhow::anyhow!("Failed to build set file input params: {}", e))?;
+
+    self.page.execute(params).await.context("failed to set input files")?;
+    Ok(())
+  }
+}
@@ -0,0 +1,242 @@
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use chromiumoxide::{Page, cdp::browser_protocol::browser::BrowserContextId};
+use tokio::{sync::{mpsc, watch}, time::sleep_until};
+use crate::browser::{
+  browser_constroller::BrowserController,
+  console::ConsoleMonitor,
+  crashes::CrashWatcher,
+  dialogs::{DialogHandler, OpenedDialog},
+  navigation::NavigationMonitor,
+  network_monitor::NetworkMonitor,
+};
+use crate::models::{
+  dialog_policy::DialogPolicy,
+  tab_selector::TabSelector,
+  task_error::TaskError,
+};
+
+pub struct Tab{
+  /// position in the order the task's tabs were opened, starting at 0
+  pub index: usize,
+  pub page: Page,
+  pub network: NetworkMonitor,
+  pub navigation: NavigationMonitor,
+  pub console: ConsoleMonitor,
+  /// set once a step has switched to the tab, so `wait_for_popup` only picks up new ones
+  claimed: bool,
+  _dialogs: DialogHandler,
+  _crashes: CrashWatcher,
+}
+
+/// every tab open in a task's browser context, including popups the page opened itself
+pub struct TabSet{
+  context: BrowserContextId,
+  tabs: Vec<Tab>,
+  /// tab indexes in the order they were activated; the last one is active
+  history: Vec<usize>,
+  opened: usize,
+  dialog_policy: watch::Sender<DialogPolicy>,
+  dialogs_tx: mpsc::UnboundedSender<OpenedDialog>,
+  dialogs_rx: mpsc::UnboundedReceiver<OpenedDialog>,
+  /// the first tab whose renderer crashed
+  crashed: watch::Sender<Option<usize>>,
+}
+
+impl TabSet{
+  pub async fn new(context: BrowserContextId, page: Page) -> Result<Self>{
+    let (dialog_policy, _) = watch::channel(DialogPolicy::default());
+    let (dialogs_tx, dialogs_rx) = mpsc::unbounded_channel();
+    let (crashed, _) = watch::channel(None);
+    let mut tabs = Self{
+      context,
+      tabs: Vec::new(),
+      history: vec![0],
+      opened: 0,
+      dialog_policy,
+      dialogs_tx,
+      dialogs_rx,
+      crashed,
+    };
+    tabs.track(page, true).await?;
+    Ok(tabs)
+  }
+
+  /// the policy dialogs in any of the tabs are answered with from now on
+  pub fn set_dialog_policy(&self, policy: DialogPolicy){
+    self.dialog_policy.send_replace(policy);
+  }
+
+  /// dialogs answered since the last call
+  pub fn take_dialogs(&mut self) -> Vec<OpenedDialog>{
+    let mut dialogs = Vec::new();
+    while let Ok(dialog) = self.dialogs_rx.try_recv(){
+      dialogs.push(dialog);
+    }
+    dialogs
+  }
+
+  /// changes to the index of the first tab whose renderer crashes
+  pub fn crashes(&self) -> watch::Receiver<Option<usize>>{
+    self.crashed.subscribe()
+  }
+
+  pub fn context(&self) -> &BrowserContextId{
+    &self.context
+  }
+
+  pub fn pages(&self) -> impl Iterator<Item = &Page>{
+    self.tabs.iter().map(|tab| &tab.page)
+  }
+
+  /// the open tab with `index`
+  pub fn get(&self, index: usize) -> Option<&Tab>{
+    self.tabs.iter().find(|tab| tab.index == index)
+  }
+
+  pub fn active(&self) -> &Tab{
+    let index = *self.history.last().expect("a task always has an active tab");
+    self.tabs.iter()
+      .find(|tab| tab.index == index)
+      .expect("the active tab is open")
+  }
+
+  /// picks up tabs opened and closed by the page since the last call. a closed active tab
+  /// hands over to the previously active one; the last remaining tab is always kept
+  pub async fn refresh(&mut self, browser: &BrowserController) -> Result<()>{
+    let targets = browser.context_targets(&self.context).await?;
+
+    if self.tabs.iter().any(|tab| targets.contains(tab.page.target_id())){
+      let closed: Vec<usize> = self.tabs.iter()
+        .filter(|tab| !targets.contains(tab.page.target_id()))
+        .map(|tab| tab.index)
+        .collect();
+      self.tabs.retain(|tab| !closed.contains(&tab.index));
+      self.history.retain(|index| !closed.contains(index));
+      if self.history.is_empty(){
+        self.history.push(self.tabs[0].index);
+      }
+    }
+
+    for target in targets{
+      if self.tabs.iter().any(|tab| *tab.page.target_id() == target){
+        continue;
+      }
+      if let Some(page) = browser.attach_page(target).await?{
+        self.track(page, false).await?;
+      }
+    }
+    Ok(())
+  }
+
+  pub async fn switch(&mut self, browser: &BrowserController, selector: &TabSelector) -> Result<()>{
+    self.refresh(browser).await?;
+    let index = match (selector.index, &selector.url, selector.latest){
+      (Some(index), _, _) => self.tabs.iter().find(|tab| tab.index == index).map(|tab| tab.index),
+      (None, Some(url), _) => {
+        let mut found = None;
+        for tab in &self.tabs{
+          if tab.page.url().await?.is_some_and(|tab_url| tab_url.contains(url.as_str())){
+            found = Some(tab.index);
+            break;
+          }
+        }
+        found
+      }
+      (None, None, true) => self.tabs.iter().map(|tab| tab.index).max(),
+      (None, None, false) => anyhow::bail!("switch_tab needs an index, url or latest"),
+    };
+
+    let index = index.with_context(|| format!("no open {}", selector))?;
+    self.activate(index).await
+  }
+
+  /// switches to the oldest tab no step has switched to yet, waiting for one to open. the tabs
+  /// are looked at again whenever the browser reports a target change
+  pub async fn wait_for_popup(&mut self, browser: &BrowserController, timeout_ms: u64) -> Result<()>{
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let mut changes = browser.target_changes().await?;
+
+    loop{
+      self.refresh(browser).await?;
+      if let Some(tab) = self.tabs.iter().find(|tab| !tab.claimed){
+        let index = tab.index;
+        return self.activate(index).await;
+      }
+
+      tokio::select!{
+        change = changes.next() => {
+          if change.is_none(){
+            anyhow::bail!("browser closed while waiting for a popup");
+          }
+        }
+        _ = sleep_until(deadline.into()) => {
+          return Err(TaskError::timeout(format!("timeout after {}ms waiting for a popup", timeout_ms)).into());
+        }
+      }
+    }
+  }
+
+  pub async fn close(&mut self, browser: &BrowserController, index: Option<usize>) -> Result<()>{
+    self.refresh(browser).await?;
+    let index = index.unwrap_or(self.active().index);
+    let position = self.tabs.iter()
+      .position(|tab| tab.index == index)
+      .with_context(|| format!("no open tab {}", index))?;
+    if self.tabs.len() == 1{
+      anyhow::bail!("cannot close the task's last tab");
+    }
+
+    let tab = self.tabs.remove(position);
+    self.history.retain(|i| *i != index);
+    if self.history.is_empty(){
+      self.history.push(self.tabs[0].index);
+    }
+    tab.page.close().await.with_context(|| format!("failed to close tab {}", index))?;
+
+    let active = self.active().index;
+    self.activate(active).await
+  }
+
+  /// closes every tab through the browser, which works even when a page is stuck in a script
+  pub async fn close_all(self, browser: &BrowserController){
+    for tab in self.tabs{
+      if let Err(e) = browser.close_target(tab.page.target_id().clone()).await{
+        eprintln!("failed to close tab {}: {}", tab.index, e);
+      }
+    }
+  }
+
+  async fn track(&mut self, page: Page, claimed: bool) -> Result<()>{
+    let network = NetworkMonitor::attach(&page).await?;
+    let navigation = NavigationMonitor::attach(&page).await?;
+    let console = ConsoleMonitor::attach(&page).await?;
+    let dialogs = DialogHandler::attach(&page, self.opened, self.dialog_policy.subscribe(), self.dialogs_tx.clone()).await?;
+    let crashes = CrashWatcher::attach(&page, self.opened, self.crashed.clone()).await?;
+    self.tabs.push(Tab{
+      index: self.opened,
+      page,
+      network,
+      navigation,
+      console,
+      claimed,
+      _dialogs: dialogs,
+      _crashes: crashes,
+    });
+    self.opened += 1;
+    Ok(())
+  }
+
+  async fn activate(&mut self, index: usize) -> Result<()>{
+    let tab = self.tabs.iter_mut()
+      .find(|tab| tab.index == index)
+      .with_context(|| format!("no open tab {}", index))?;
+    tab.claimed = true;
+    tab.page.bring_to_front().await?;
+
+    self.history.retain(|i| *i != index);
+    self.history.push(index);
+    Ok(())
+  }
+}
@@ -0,0 +1,253 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use chromiumoxide::{
+  Page,
+  cdp::browser_protocol::page::{EventFrameNavigated, EventNavigatedWithinDocument},
+  error::CdpError,
+};
+use futures::StreamExt;
+use tokio::time::{sleep, timeout};
+use crate::browser::frame::{FrameScope, context_lost};
+use crate::models::task_error::{ErrorKind, TaskError};
+use crate::models::url_pattern::UrlPattern;
+
+/// in-page waiter, installed once per document. resolves with what `predicate` returns once
+/// that is truthy, checking it once at the start, on every DOM mutation and history change, and
+/// every 100ms for what changes without touching the DOM, such as layout and transitions. a
+/// check still awaiting is not started again. a predicate that throws counts as false; its last
+/// error is handed back if the wait times out. syntax and reference errors cannot clear up by
+/// waiting and fail the wait at once. each wait is kept under its `id` in `__softlightWaits`, so
+/// one the rust side gave up on can be stopped
+const WAITER: &str = r#"(window.__softlightWait || (window.__softlightWait = (id, predicate, timeoutMs) => new Promise((resolve, reject) => {
+  const waits = window.__softlightWaits || (window.__softlightWaits = new Map());
+  let done = false;
+  let error = null;
+  let running = false;
+  const events = ['hashchange', 'popstate', 'pageshow'];
+  const stop = () => {
+    done = true;
+    waits.delete(id);
+    observer.disconnect();
+    clearInterval(interval);
+    clearTimeout(deadline);
+    for(const event of events) window.removeEventListener(event, check);
+  };
+  const finish = (met, value) => {
+    if(done) return;
+    stop();
+    resolve({ met, value, error });
+  };
+  const fail = (e) => {
+    if(done) return;
+    stop();
+    reject(e);
+  };
+  const check = async () => {
+    if(done || running) return;
+    running = true;
+    try {
+      const value = await predicate();
+      if(value) finish(true, value);
+      error = null;
+    } catch(e) {
+      if(e instanceof SyntaxError || e instanceof ReferenceError) return fail(e);
+      error = String(e && e.message || e);
+    } finally {
+      running = false;
+    }
+  };
+
+  const observer = new MutationObserver(check);
+  observer.observe(document, { subtree: true, childList: true, attributes: true, characterData: true });
+  for(const event of events) window.addEventListener(event, check);
+  const interval = setInterval(check, 100);
+  const deadline = setTimeout(() => finish(false), timeoutMs);
+  waits.set(id, () => finish(false));
+  check();
+})))"#;
+
+/// ids of in-page waits, unique for the whole run so a stop never reaches another wait
+static WAIT_IDS: AtomicU64 = AtomicU64::new(0);
+
+/// stops an in-page wait whose result is no longer awaited, such as the losing branches of an
+/// `any` wait, instead of leaving it to check until its own deadline
+struct InPageWait{
+  scope: FrameScope,
+  id: u64,
+  settled: bool,
+}
+
+impl InPageWait{
+  fn settled(mut self){
+    self.settled = true;
+  }
+}
+
+impl Drop for InPageWait{
+  fn drop(&mut self){
+    if self.settled{
+      return;
+    }
+    let scope = self.scope.clone();
+    let script = format!("window.__softlightWaits?.get({})?.()", self.id);
+    tokio::spawn(async move{
+      // the document may be gone already, taking the wait with it
+      let _ = scope.evaluate(script).await;
+    });
+  }
+}
+
+/// extra time the rust side gives the in-page deadline before giving up on the page itself
+const GRACE: Duration = Duration::from_millis(1000);
+
+/// waits in `scope` until `body`, the body of an async javascript function, returns something
+/// truthy. the wait is a single awaited promise; a navigation that destroys the document starts
+/// it over in the new one with whatever time is left, while a script that fails to compile or
+/// run fails the wait as a script error. `what` completes "timeout waiting for ..."
+pub async fn wait_until(scope: &FrameScope, body: &str, timeout_ms: u64, what: &str) -> Result<()>{
+  wait_for(scope, body, timeout_ms, what).await?;
+  Ok(())
+}
+
+/// like `wait_until`, returning the truthy value `body` returned
+pub async fn wait_for(scope: &FrameScope, body: &str, timeout_ms: u64, what: &str) -> Result<serde_json::Value>{
+  let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+  let id = WAIT_IDS.fetch_add(1, Ordering::Relaxed);
+
+  loop{
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let script = format!(
+      "{}({}, async () => {{ {} }}, {})",
+      WAITER,
+      id,
+      body,
+      remaining.as_millis()
+    );
+
+    let wait = InPageWait{scope: scope.clone(), id, settled: false};
+    let outcome = timeout(remaining + GRACE, scope.evaluate(script)).await;
+    wait.settled();
+    let outcome = match outcome{
+      Ok(outcome) => outcome,
+      Err(_) => return Err(TaskError::timeout(format!(
+        "timeout after {}ms waiting for {}: the page stopped responding", timeout_ms, what
+      )).into()),
+    };
+
+    match outcome{
+      Ok(result) => {
+        let result = result.value().cloned().unwrap_or_default();
+        if result["met"].as_bool() == Some(true){
+          return Ok(result["value"].clone());
+        }
+        let message = match result["error"].as_str(){
+          Some(error) => format!("timeout after {}ms waiting for {}: {}", timeout_ms, what, error),
+          None => format!("timeout after {}ms waiting for {}", timeout_ms, what),
+        };
+        return Err(TaskError::timeout(message).into());
+      }
+      Err(e) => {
+        if let Some(CdpError::JavascriptException(details)) = e.downcast_ref::<CdpError>(){
+          let message = details.exception.as_ref()
+            .and_then(|exception| exception.description.clone())
+            .unwrap_or_else(|| details.text.clone());
+          return Err(TaskError::new(ErrorKind::ScriptError, format!(
+            "script error waiting for {}: {}", what, message
+          )).into());
+        }
+        if !context_lost(&e){
+          return Err(e);
+        }
+        // the document went away mid-wait, usually to a navigation; an iframe's new document
+        // has a context of its own to look up
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero(){
+          return Err(e.context(TaskError::timeout(format!("timeout after {}ms waiting for {}", timeout_ms, what))));
+        }
+        scope.reattach(remaining.as_millis() as u64).await
+          .with_context(|| format!("timeout after {}ms waiting for {}: the frame went away", timeout_ms, what))?;
+        sleep(Duration::from_millis(50)).await;
+      }
+    }
+  }
+}
+
+/// waits until the top-level url matches `url`. besides the in-page wait, chrome's own
+/// navigation events are matched as they arrive, so client-side route changes and the start of
+/// a navigation resolve without waiting for a frame or the new document
+pub async fn wait_for_url(page: &Page, url: &UrlPattern, timeout_ms: u64) -> Result<()>{
+  let matcher = url_matcher(url)?;
+  let what = format!("url {}", url);
+  let main_frame = page.mainframe().await?;
+  let mut within_document = page.event_listener::<EventNavigatedWithinDocument>().await?;
+  let mut navigated = page.event_listener::<EventFrameNavigated>().await?;
+
+  let scope = FrameScope::main(page);
+  let body = format!("return ({})(location.href);", matcher);
+  let in_page = wait_until(&scope, &body, timeout_ms, &what);
+  tokio::pin!(in_page);
+
+  loop{
+    let navigated_to = tokio::select!{
+      result = &mut in_page => return result,
+      Some(event) = within_document.next() => {
+        (main_frame.as_ref() == Some(&event.frame_id)).then(|| event.url.clone())
+      }
+      Some(event) = navigated.next() => {
+        event.frame.parent_id.is_none()
+          .then(|| format!("{}{}", event.frame.url, event.frame.url_fragment.as_deref().unwrap_or_default()))
+      }
+    };
+
+    let Some(navigated_to) = navigated_to else{
+      continue;
+    };
+    let script = format!("({})({})", matcher, serde_json::Value::from(navigated_to));
+    // the document may be on its way out; the in-page wait picks up the new one
+    if let Ok(result) = page.evaluate(script).await
+      && result.value().and_then(|v| v.as_bool()) == Some(true)
+    {
+      return Ok(());
+    }
+  }
+}
+
+/// a javascript function taking a url and telling whether it matches `url`
+pub fn url_matcher(url: &UrlPattern) -> Result<String>{
+  let parts = url.parts();
+  if parts.is_empty(){
+    anyhow::bail!("url wait needs a pattern, path, query or hash");
+  }
+
+  Ok(format!(
+    r#"
+    ((href) => {{
+      const mode = {mode};
+      const parts = {parts};
+      const glob = (pattern) => new RegExp('^' + pattern
+        .replace(/[.+^${{}}()|[\]\\]/g, '\\$&')
+        .replace(/\*\*|\*|\?/g, (token) => token === '**' ? '.*' : token === '*' ? '[^/]*' : '.') + '$');
+      let url;
+      try {{
+        url = new URL(href);
+      }} catch(e) {{
+        return false;
+      }}
+      return Object.entries(parts).every(([part, pattern]) => {{
+        const value = part === 'search' || part === 'hash' ? url[part].slice(1) : url[part];
+        switch(mode){{
+          case 'exact': return value === pattern;
+          case 'regex': return new RegExp(pattern).test(value);
+          case 'glob': return glob(pattern).test(value);
+          default: return value.includes(pattern);
+        }}
+      }});
+    }})
+    "#,
+    mode = serde_json::to_string(&url.mode)?,
+    parts = serde_json::Value::Object(
+      parts.into_iter().map(|(part, value)| (part.to_string(), value.into())).collect()
+    ),
+  ))
+}
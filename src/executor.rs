@@ -1,127 +1,686 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 use anyhow::{Context, Result};
-use chromiumoxide::Page;
+use chromiumoxide::{Page, cdp::browser_protocol::browser::BrowserContextId, error::CdpError};
 use chrono::Utc;
-use tokio::time::sleep;
+use tokio::{sync::watch, time::{sleep, timeout}};
+use crate::assertions::check_assertion;
+use crate::auth::AuthStore;
 use crate::browser::{
+  actionability::{Actionability, Checks, wait_for_actionable},
   browser_constroller::BrowserController,
+  dialogs::OpenedDialog,
+  frame::FrameScope,
+  keyboard::{self, KeyChord},
+  mouse,
+  network_monitor::NetworkMonitor,
   page_extension::PageExtension,
+  selector::{ElementHandle, js_string, query_all_expression, query_expression},
+  tabs::{Tab, TabSet},
+  waiter::{wait_for_url, wait_until},
 };
 use crate::models::{
   action::Action,
+  assertion::Assertion,
+  assertion_failure::AssertionFailure,
+  cookie::Cookie,
+  auth_profile::AuthProfile,
   captured_state::CapturedState,
+  dialog_record::DialogRecord,
   element_state::ElementState,
   execution_result::ExecutionResult,
+  failure_capture::FailureCapture,
+  frame_selector::FrameSelector,
+  mouse_button::MouseButton,
+  mouse_target::MouseTarget,
+  navigation::NavigationOptions,
+  retry_policy::RetryPolicy,
   scroll_direction::ScrollDirection,
   step::Step,
+  step_result::{StepResult, StepStatus},
+  setup::Setup,
+  storage_state::{OriginStorage, StorageState},
   task::Task,
+  task_error::{ErrorKind, TaskError},
   wait_condition::WaitCondition,
 };
+use crate::variables::{Template, Variables};
 use crate::state_capture::{
   CaptureOptions,
+  capture_screenshot,
   extract_viewport_info,
   extract_page_metadata,
   capture_settled,
+  clear_dialog,
+  draw_dialog,
 };
 
+/// how long each part of a failure capture may take, so a hung page cannot hold up the report
+const FAILURE_CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// settings resolved for a single step from the step and its task
+struct StepOptions<'a>{
+  actionability: Actionability,
+  retry: Option<&'a RetryPolicy>,
+  /// how long one attempt of the action and wait may take
+  timeout_ms: u64,
+}
+
+/// what a task has produced so far. it lives outside the task's future so that a task stopped
+/// by its time budget or a crash still reports the steps it ran and the states it captured
+#[derive(Default)]
+struct Progress{
+  captured_states: Vec<CapturedState>,
+  steps: Vec<StepResult>,
+  errors: Vec<TaskError>,
+  assertion_failures: Vec<AssertionFailure>,
+  failures: Vec<FailureCapture>,
+  /// dialogs opened while signing in or opening the starting url
+  setup_dialogs: Vec<DialogRecord>,
+  /// the step being run and the attempts made at it, until its result is recorded
+  current: Option<(usize, String)>,
+  attempts: u32,
+}
+
+impl Progress{
+  /// records `error` for a task stopped from outside, failing the step it was in
+  fn stop(&mut self, mut error: TaskError){
+    if let Some((step_index, step_name)) = self.current.take(){
+      self.steps.push(StepResult{
+        step_index,
+        step_name: step_name.clone(),
+        status: StepStatus::Failed,
+        attempts: self.attempts,
+        error: Some(error.message.clone()),
+        dialogs: Vec::new(),
+      });
+      error.step_index = Some(step_index);
+      error.step_name = Some(step_name);
+    }
+    self.errors.push(error);
+  }
+
+  fn crashed(&self) -> bool{
+    self.errors.iter().any(|e| e.kind == ErrorKind::BrowserCrashed)
+  }
+}
+
 pub struct TaskExecutor{
   browser: BrowserController,
+  retained: HashSet<String>,
+  sessions: Mutex<HashMap<String, BrowserContextId>>,
+  auth: Option<AuthStore>,
+  crash_retries: u32,
 }
 
 impl TaskExecutor{
   pub async fn new(viewport_width: u32, viewport_height: u32) -> Result<Self>{
     let browser = BrowserController::with_viewport(viewport_width, viewport_height).await?;
-    Ok(Self{browser})
+    Ok(Self{
+      browser,
+      retained: HashSet::new(),
+      sessions: Mutex::new(HashMap::new()),
+      auth: None,
+      crash_retries: 2,
+    })
+  }
+
+  /// how many more times a task is run after the browser or one of its tabs crashed
+  pub fn set_crash_retries(&mut self, retries: u32){
+    self.crash_retries = retries;
+  }
+
+  /// directory holding the auth profiles tasks with `auth_required` sign in through
+  pub fn set_auth_dir(&mut self, dir: PathBuf){
+    self.auth = Some(AuthStore::new(dir));
   }
 
+  /// keeps the browser context of the given tasks alive after they finish so that tasks
+  /// declaring `share_session_with` can run in it
+  pub fn retain_sessions(&mut self, task_ids: impl IntoIterator<Item = String>){
+    self.retained.extend(task_ids);
+  }
+
+  pub async fn close(self) -> Result<()>{
+    let sessions: Vec<_> = self.sessions.into_inner()
+      .unwrap_or_else(|e| e.into_inner())
+      .into_values()
+      .collect::<HashSet<_>>()
+      .into_iter()
+      .collect();
+    for context in sessions{
+      if let Err(e) = self.browser.dispose_context(context).await{
+        eprintln!("{}", e);
+      }
+    }
+    self.browser.close().await
+  }
+
+  /// runs `task`, running it again from the start after a crash, in a relaunched browser if
+  /// the whole browser went down
   pub async fn execute(&self, task: Task) -> Result<ExecutionResult>{
     let start_time = Instant::now();
-    let page = self.browser.new_page().await?;
+    let mut crashes = 0;
+    loop{
+      if self.browser.relaunch_if_crashed().await?{
+        eprintln!("relaunched the browser after it crashed");
+        // contexts kept for session sharing went down with it
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner()).clear();
+      }
 
-    if let Some(setup) = &task.task_def.setup{
-      if let Some(cookies) = &setup.cookies{
-        self.browser.set_cookies(&page, cookies.clone()).await?;
+      // the launch this attempt runs on; another task may relaunch the browser meanwhile
+      let connection = self.browser.connection();
+      let outcome = self.execute_once(&task, connection.clone()).await;
+      let crashed = match &outcome{
+        Ok(progress) => progress.crashed(),
+        Err(_) => !*connection.borrow(),
+      };
+      if crashed{
+        crashes += 1;
       }
-      if let Some(local_storage) = &setup.local_storage{
-        self.browser.set_local_storage(&page, local_storage.clone()).await?;
+      if !crashed || crashes > self.crash_retries{
+        let progress = outcome?;
+        return Ok(task_result(&task, progress, start_time.elapsed().as_millis() as u64, crashes));
       }
-      if let Some(starting_url) = &setup.starting_url{
-        let full_url = format!("{}{}", task.task_def.base_url, starting_url);
-        page.goto(&full_url).await?;
+
+      eprintln!(
+        "{}: browser crashed, running the task again ({}/{})",
+        task.task_def.id, crashes, self.crash_retries
+      );
+    }
+  }
+
+  /// runs `task` once on the browser launch whose `connection` is given. everything the run
+  /// produced is returned even when cleaning up after it fails
+  async fn execute_once(&self, task: &Task, connection: watch::Receiver<bool>) -> Result<Progress>{
+    let connected = || *connection.borrow();
+    let task_id = task.task_def.id.clone();
+    let (context, owned) = match &task.task_def.share_session_with{
+      Some(source) => (self.shared_session(source)?, false),
+      None => (self.browser.create_context().await?, true),
+    };
+
+    let tabs = match self.browser.new_page(&context).await{
+      Ok(page) => TabSet::new(context.clone(), page).await,
+      Err(e) => Err(e),
+    };
+    let mut progress = Progress::default();
+    let outcome = match tabs{
+      Ok(mut tabs) => {
+        let crashed = crash(tabs.crashes(), connection.clone());
+        let budget = async{
+          match task.task_def.timeout_ms{
+            Some(budget) => {
+              sleep(Duration::from_millis(budget)).await;
+              budget
+            }
+            None => std::future::pending().await,
+          }
+        };
+
+        let mut outcome = Ok(());
+        let stopped = tokio::select!{
+          result = self.run(&mut tabs, task, &mut progress) => {
+            outcome = result;
+            None
+          }
+          error = crashed => Some(error),
+          budget = budget => Some(TaskError::timeout(format!("task timed out after {}ms", budget))),
+        };
+
+        if let Some(error) = stopped{
+          let timed_out = error.kind == ErrorKind::Timeout;
+          let (step_index, step_name) = match &progress.current{
+            Some((step_index, step_name)) => (Some(*step_index), step_name.clone()),
+            None => (None, "setup".to_string()),
+          };
+          progress.stop(error);
+          if timed_out{
+            progress.failures.push(capture_failure(tabs.active(), step_index, &step_name).await);
+          }
+        }
+        // a browser that is gone has taken its tabs with it
+        if connected(){
+          tabs.close_all(&self.browser).await;
+        }
+        outcome
       }
+      Err(e) => Err(e),
+    };
+    if let Err(e) = outcome{
+      progress.errors.push(task_error(&e));
     }
 
-    let mut captured_states = Vec::new();
+    if !connected(){
+      return Ok(progress);
+    }
+    if self.retained.contains(&task_id){
+      self.sessions.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(task_id, context);
+    }else if owned
+      && let Err(e) = self.browser.dispose_context(context).await
+    {
+      eprintln!("{}: {:#}", task_id, e);
+    }
+
+    Ok(progress)
+  }
+
+  fn shared_session(&self, task_id: &str) -> Result<BrowserContextId>{
+    self.sessions.lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .get(task_id)
+      .cloned()
+      .with_context(|| format!("no session to share from task '{}'; it must run earlier in the same batch", task_id))
+  }
+
+  async fn run(&self, tabs: &mut TabSet, task: &Task, progress: &mut Progress) -> Result<()>{
+    let mut vars = Variables::new(&task.task_def.vars);
+    let base_url = vars.render(&task.task_def.base_url)?;
+    let task_dialog_policy = task.task_def.dialogs.as_ref()
+      .map(|policy| policy.render(&vars))
+      .transpose()?
+      .unwrap_or_default();
+    tabs.set_dialog_policy(task_dialog_policy);
+
+    let setup = match &task.task_def.setup{
+      Some(setup) => self.setup(tabs, task, setup, &vars, &base_url).await,
+      None => Ok(()),
+    };
+    progress.setup_dialogs = tabs.take_dialogs().into_iter().map(|dialog| dialog.record).collect();
+    if let Err(e) = setup{
+      let mut error = task_error(&e);
+      if error.kind != ErrorKind::BrowserCrashed{
+        error.kind = ErrorKind::SetupFailed;
+        progress.failures.push(capture_failure(tabs.active(), None, "setup").await);
+      }
+      error.message = format!("setup failed: {:#}", e);
+      progress.errors.push(error);
+      return Ok(());
+    }
 
     for (idx, step) in task.task_def.steps.iter().enumerate(){
-      match self.execute_step(&page, step, &task.task_def.base_url).await{
-        Ok(()) => {
-          if let Some(wait) = &step.wait{
-            self.wait_for_condition(&page, wait).await?;
+      let options = StepOptions{
+        actionability: Actionability{
+          timeout_ms: task.task_def.actionability_timeout_ms,
+          force: step.force,
+        },
+        retry: step.retry.as_ref().or(task.task_def.retry.as_ref()),
+        timeout_ms: step.timeout_ms.unwrap_or(task.task_def.step_timeout_ms),
+      };
+      let dialog_policy = step.dialogs.as_ref()
+        .or(task.task_def.dialogs.as_ref())
+        .map(|policy| policy.render(&vars))
+        .transpose()?
+        .unwrap_or_default();
+      tabs.set_dialog_policy(dialog_policy.clone());
+      progress.current = Some((idx, step.name.clone()));
+      progress.attempts = 0;
+      let mut outcome = self.execute_with_retry(tabs, &mut vars, step, &base_url, &options, &mut progress.attempts).await;
+
+      let mut failed_assertions = Vec::new();
+      if outcome.is_ok() && !step.assert.is_empty(){
+        outcome = match self.check_assertions(&tabs.active().page, &vars, idx, step, &options).await{
+          Ok(failed) if failed.is_empty() => Ok(()),
+          Ok(failed) => {
+            let summary = failed.iter()
+              .map(|f| format!("{} expected {}, got '{}'", f.kind, f.expected, f.actual))
+              .collect::<Vec<_>>()
+              .join("; ");
+            let mut error = TaskError::new(ErrorKind::AssertionFailed, format!("assertion failed: {}", summary));
+            error.selector = failed.iter().find_map(|f| f.selector.clone());
+            failed_assertions = failed;
+            Err(error.into())
           }
+          Err(e) => Err(e),
+        };
+      }
 
+      let opened = tabs.take_dialogs();
+      if dialog_policy.capture{
+        for dialog in &opened{
+          let tab = tabs.get(dialog.tab).unwrap_or(tabs.active());
+          match self.capture_dialog_state(tab, idx, step, dialog).await{
+            Ok(state) => progress.captured_states.push(state),
+            Err(e) => eprintln!("failed to capture {} dialog of step '{}': {:#}", dialog.record.kind, step.name, e),
+          }
+        }
+      }
+      let dialogs: Vec<DialogRecord> = opened.into_iter().map(|dialog| dialog.record).collect();
+
+      // the step stays current until its result is recorded, so a task stopped while its state
+      // or failure is captured is reported against it
+      let attempts = progress.attempts;
+      let stop = match outcome{
+        Ok(()) => {
           if step.capture{
-            let state = self.capture_state(&page, idx, step).await?;
-            captured_states.push(state);
+            let state = self.capture_state(tabs.active(), idx, &step.name, step.description.clone(), dialogs.clone()).await?;
+            progress.captured_states.push(state);
           }
+          progress.steps.push(StepResult{
+            step_index: idx,
+            step_name: step.name.clone(),
+            status: StepStatus::Succeeded,
+            attempts,
+            error: None,
+            dialogs,
+          });
+          false
+        }
+        Err(e) if step.optional => {
+          progress.steps.push(StepResult{
+            step_index: idx,
+            step_name: step.name.clone(),
+            status: StepStatus::Skipped,
+            attempts,
+            error: Some(format!("{:#}", e)),
+            dialogs,
+          });
+          false
         }
         Err(e) => {
-          return Ok(ExecutionResult{
-            task_id: task.task_def.id.clone(),
-            app: task.task_def.app.clone(),
-            description: task.task_def.description.clone(),
-            success: false,
-            captured_states,
-            error: Some(format!("step '{}' failed: {}", step.name, e)),
-            execution_time_ms: start_time.elapsed().as_millis() as u64,
+          let mut error = task_error(&e);
+          error.step_index = Some(idx);
+          error.step_name = Some(step.name.clone());
+          if error.url.is_none(){
+            error.url = tabs.active().page.url().await.ok().flatten();
+          }
+          if error.kind != ErrorKind::BrowserCrashed{
+            progress.failures.push(capture_failure(tabs.active(), Some(idx), &step.name).await);
+          }
+          progress.steps.push(StepResult{
+            step_index: idx,
+            step_name: step.name.clone(),
+            status: StepStatus::Failed,
+            attempts,
+            error: Some(format!("{:#}", e)),
+            dialogs,
           });
+          progress.errors.push(error);
+          progress.assertion_failures.append(&mut failed_assertions);
+          !step.continue_on_error
         }
+      };
+      progress.current = None;
+      if stop{
+        break;
       }
     }
 
-    Ok(ExecutionResult{
-      task_id: task.task_def.id,
-      app: task.task_def.app,
-      description: task.task_def.description,
-      success: true,
-      captured_states,
-      error: None,
-      execution_time_ms: start_time.elapsed().as_millis() as u64,
-    })
+    Ok(())
   }
 
-  async fn execute_step(&self, page: &Page, step: &Step, base_url: &str) -> Result<()>{
-    match &step.action{
-      Action::Navigate{url} => {
+  /// signs in, seeds cookies and storage and opens the starting url
+  async fn setup(&self, tabs: &mut TabSet, task: &Task, setup: &Setup, vars: &Variables, base_url: &str) -> Result<()>{
+    if setup.auth_required{
+      let profile = setup.auth_profile.as_deref().unwrap_or(&task.task_def.app);
+      self.authenticate(tabs, profile).await
+        .with_context(|| format!("failed to sign in with auth profile '{}'", profile))?;
+    }
+
+    if let Some(cookies) = &setup.cookies{
+      let cookies: Vec<Cookie> = cookies.iter()
+        .map(|cookie| match (&cookie.domain, &cookie.url){
+          (None, None) => Cookie{url: Some(base_url.to_string()), ..cookie.clone()},
+          _ => cookie.clone(),
+        })
+        .collect();
+      self.browser.add_context_cookies(tabs.context(), &cookies).await?;
+    }
+
+    let mut origins = setup.storage.clone();
+    if let Some(local_storage) = &setup.local_storage{
+      origins.push(OriginStorage{
+        origin: origin_of(base_url),
+        local_storage: local_storage.clone(),
+        session_storage: HashMap::new(),
+        indexed_db: Vec::new(),
+      });
+    }
+    let tab = tabs.active();
+    self.browser.seed_storage(&tab.page, &origins).await?;
+    if let Some(starting_url) = &setup.starting_url{
+      let full_url = format!("{}{}", base_url, vars.render(starting_url)?);
+      tab.navigation.navigate(&tab.page, &full_url, &setup.navigation).await?;
+    }
+    Ok(())
+  }
+
+  /// starts the task's context signed in: from the profile's saved session while its check
+  /// passes, otherwise by running the login steps and saving the session they leave behind
+  async fn authenticate(&self, tabs: &mut TabSet, name: &str) -> Result<()>{
+    let auth = self.auth.as_ref().context("no auth directory configured")?;
+    let profile = auth.profile(name).await?;
+
+    let saved = auth.state(name).await?;
+    if let Some(state) = &saved
+      && self.restore_session(tabs, &profile, state).await?
+    {
+      return Ok(());
+    }
+
+    let _login = auth.lock(name).await;
+    // another task may have logged in while this one waited for the lock
+    let current = auth.state(name).await?;
+    if let Some(state) = &current
+      && current != saved
+      && self.restore_session(tabs, &profile, state).await?
+    {
+      return Ok(());
+    }
+
+    self.login(tabs, &profile).await?;
+    if !self.session_valid(tabs, &profile).await?{
+      anyhow::bail!("session check still fails after the login steps");
+    }
+    let state = self.export_state(tabs).await?;
+    auth.save_state(name, &state).await
+  }
+
+  /// applies a saved session and tells whether it is still signed in
+  async fn restore_session(&self, tabs: &TabSet, profile: &AuthProfile, state: &StorageState) -> Result<bool>{
+    self.browser.add_context_cookies(tabs.context(), &state.cookies).await?;
+    self.browser.seed_storage(&tabs.active().page, &state.origins).await?;
+    self.session_valid(tabs, profile).await
+  }
+
+  async fn session_valid(&self, tabs: &TabSet, profile: &AuthProfile) -> Result<bool>{
+    let Some(check) = &profile.check else{
+      return Ok(true);
+    };
+
+    let vars = Variables::new(&profile.vars);
+    let tab = tabs.active();
+    let url = format!("{}{}", vars.render(&profile.base_url)?, vars.render(&check.url)?);
+    let response = tab.navigation.navigate(&tab.page, &url, &NavigationOptions::default()).await?;
+    if matches!(response.status, Some(401 | 403)){
+      return Ok(false);
+    }
+
+    let scope = FrameScope::main(&tab.page);
+    for assertion in &check.assert{
+      let assertion = assertion.render(&vars)?;
+      if !check_assertion(&scope, &assertion).await?.passed{
+        return Ok(false);
+      }
+    }
+    Ok(true)
+  }
+
+  async fn login(&self, tabs: &mut TabSet, profile: &AuthProfile) -> Result<()>{
+    let mut vars = Variables::new(&profile.vars);
+    let base_url = vars.render(&profile.base_url)?;
+
+    for (idx, step) in profile.login.iter().enumerate(){
+      let options = StepOptions{
+        actionability: Actionability{
+          timeout_ms: profile.actionability_timeout_ms,
+          force: step.force,
+        },
+        retry: step.retry.as_ref(),
+        timeout_ms: step.timeout_ms.unwrap_or(profile.step_timeout_ms),
+      };
+      self.execute_with_retry(tabs, &mut vars, step, &base_url, &options, &mut 0).await
+        .with_context(|| format!("login step '{}' failed", step.name))?;
+
+      let failed = self.check_assertions(&tabs.active().page, &vars, idx, step, &options).await?;
+      if let Some(failure) = failed.first(){
+        anyhow::bail!(
+          "login step '{}' failed: {} expected {}, got '{}'",
+          step.name, failure.kind, failure.expected, failure.actual
+        );
+      }
+    }
+    Ok(())
+  }
+
+  /// cookies of the whole context plus the storage of every origin open in a tab
+  async fn export_state(&self, tabs: &TabSet) -> Result<StorageState>{
+    let cookies = self.browser.context_cookies(tabs.context()).await?;
+    let mut origins: Vec<OriginStorage> = Vec::new();
+    for page in tabs.pages(){
+      if let Some(storage) = self.browser.read_storage(page).await?
+        && !origins.iter().any(|o| o.origin == storage.origin)
+      {
+        origins.push(storage);
+      }
+    }
+    Ok(StorageState{cookies, origins})
+  }
+
+  /// runs the step's action and wait, retrying both together as the policy allows and cutting
+  /// each attempt off after the step timeout; returns the outcome of the last attempt and counts
+  /// the attempts made in `attempts`
+  async fn execute_with_retry(
+    &self,
+    tabs: &mut TabSet,
+    vars: &mut Variables,
+    step: &Step,
+    base_url: &str,
+    options: &StepOptions<'_>,
+    attempts: &mut u32,
+  ) -> Result<()>{
+    let policy = options.retry;
+    let max_attempts = policy.map_or(1, |p| p.attempts.max(1));
+
+    loop{
+      *attempts += 1;
+      let attempt = self.attempt_step(tabs, vars, step, base_url, options);
+      let outcome = timeout(Duration::from_millis(options.timeout_ms), attempt).await
+        .unwrap_or_else(|_| Err(TaskError::timeout(format!("step timed out after {}ms", options.timeout_ms)).into()));
+
+      match (outcome, policy){
+        (Err(e), Some(policy)) if *attempts < max_attempts && policy.is_retryable(task_error(&e).kind) => {
+          sleep(policy.delay(*attempts)).await;
+        }
+        (outcome, _) => return outcome,
+      }
+    }
+  }
+
+  /// one attempt at the step's action followed by its wait
+  async fn attempt_step(
+    &self,
+    tabs: &mut TabSet,
+    vars: &mut Variables,
+    step: &Step,
+    base_url: &str,
+    options: &StepOptions<'_>,
+  ) -> Result<()>{
+    self.execute_action(tabs, vars, step, base_url, options).await?;
+    let Some(wait) = step.wait.render(vars)? else{
+      return Ok(());
+    };
+    let tab = tabs.active();
+    let frame = wait.frame.as_ref().or(step.frame.as_ref());
+    let scope = frame_scope(&tab.page, vars, frame, options).await?;
+    self.wait_for_condition(&scope, &tab.network, &wait.condition).await
+  }
+
+  /// runs tab actions against the task's tabs and every other action in the active tab
+  async fn execute_action(
+    &self,
+    tabs: &mut TabSet,
+    vars: &mut Variables,
+    step: &Step,
+    base_url: &str,
+    options: &StepOptions<'_>,
+  ) -> Result<()>{
+    tabs.refresh(&self.browser).await?;
+    match step.action.render(vars)?{
+      Action::SwitchTab{tab} => tabs.switch(&self.browser, &tab).await,
+      Action::WaitForPopup{timeout_ms} => tabs.wait_for_popup(&self.browser, timeout_ms).await,
+      Action::CloseTab{index} => tabs.close(&self.browser, index).await,
+      Action::Navigate{url, navigation} => {
         let full_url = if url.starts_with("http"){
-          url.clone()
+          url
         }else{
           format!("{}{}", base_url, url)
         };
-        page.goto(&full_url).await?;
+        let tab = tabs.active();
+        tab.navigation.navigate(&tab.page, &full_url, &navigation).await?;
+        Ok(())
+      }
+      action => {
+        let scope = frame_scope(&tabs.active().page, vars, step.frame.as_ref(), options).await?;
+        self.execute_step(&scope, vars, &action, &options.actionability).await
       }
-      Action::Click{selector, wait_before_ms} => {
+    }
+  }
+
+  async fn execute_step(
+    &self,
+    scope: &FrameScope,
+    vars: &mut Variables,
+    action: &Action,
+    actionability: &Actionability,
+  ) -> Result<()>{
+    let page = scope.page();
+    match action{
+      Action::Click{target, wait_before_ms, button, click_count} => {
         if let Some(wait) = wait_before_ms{
           sleep(Duration::from_millis(*wait)).await;
         }
-        let element = page.find_element(selector).await
-          .with_context(|| format!("element not found: {}", selector))?;
-        element.click().await?;
+        let point = mouse::resolve_target(scope, target, Checks::CLICK, actionability).await?;
+        mouse::click(page, point, *button, *click_count).await?;
+      }
+      Action::DoubleClick{target} => {
+        let point = mouse::resolve_target(scope, target, Checks::CLICK, actionability).await?;
+        mouse::click(page, point, MouseButton::Left, 2).await?;
+      }
+      Action::RightClick{target} => {
+        let point = mouse::resolve_target(scope, target, Checks::CLICK, actionability).await?;
+        mouse::click(page, point, MouseButton::Right, 1).await?;
+      }
+      Action::MouseDown{target, button} => {
+        let point = mouse::resolve_target(scope, target, Checks::CLICK, actionability).await?;
+        mouse::move_to(page, point, 0).await?;
+        mouse::down(page, point, *button, 1).await?;
+      }
+      Action::MouseUp{target, button} => {
+        let point = mouse::resolve_target(scope, target, Checks::DROP_TARGET, actionability).await?;
+        mouse::move_to(page, point, mouse::button_mask(*button)).await?;
+        mouse::up(page, point, *button, 1).await?;
+      }
+      Action::Drag{from, to, steps} => {
+        let start = mouse::resolve_target(scope, from, Checks::HOVER, actionability).await?;
+        let end = mouse::resolve_target(scope, to, Checks::DROP_TARGET, actionability).await?;
+        mouse::drag(page, start, end, *steps).await
+          .with_context(|| format!("failed to drag {} to {}", from, to))?;
       }
       Action::Type{selector, value, clear_first} => {
-        let element = page.find_element(selector).await
-          .with_context(|| format!("input not found: {}", selector))?;
-
+        let point = mouse::resolve_target(scope, &MouseTarget::element(selector), Checks::CLICK, actionability).await?;
+        mouse::click(page, point, MouseButton::Left, 1).await?;
         if *clear_first{
-          element.click().await?;
-          #[cfg(target_os="macos")]
-          element.press_key("Meta+a").await?;
-          #[cfg(not(target_os="macos"))]
-          element.press_key("Control+a").await?;
-          element.press_key("Backspace").await?;
+          KeyChord::parse("ControlOrMeta+a")?.press(page).await?;
+          KeyChord::parse("Backspace")?.press(page).await?;
         }
 
-        element.type_str(value).await?;
+        keyboard::type_text(page, value).await?;
       }
       Action::Wait{duration_ms} => {
         sleep(Duration::from_millis(*duration_ms)).await;
@@ -133,103 +692,446 @@ impl TaskExecutor{
           ScrollDirection::Left => (-*amount, 0),
           ScrollDirection::Right => (*amount, 0),
         };
-        page.evaluate(format!("window.scrollBy({}, {})", x, y)).await?;
+        scope.evaluate(format!("window.scrollBy({}, {})", x, y)).await?;
       }
       Action::Hover{selector} => {
-        let element = page.find_element(selector).await
-          .with_context(|| format!("element not found: {}", selector))?;
-        element.hover().await?;
+        let point = mouse::resolve_target(scope, &MouseTarget::element(selector), Checks::HOVER, actionability).await?;
+        mouse::move_to(page, point, 0).await?;
       }
-      Action::Press{key} => {
-        page.evaluate(format!(
-          "window.dispatchEvent(new KeyboardEvent('keydown', {{ key: '{}' }}))",
-          key
-        )).await?;
+      Action::Select{selector, value, label, index} => {
+        if value.is_empty() && label.is_empty() && index.is_empty(){
+          anyhow::bail!("select requires at least one value, label or index");
+        }
+        if !actionability.force{
+          wait_for_actionable(scope, selector, None, Checks::INPUT, actionability).await?;
+        }
+        let element = ElementHandle::find(scope, selector).await?;
+        element.select_options(value, label, index).await
+          .with_context(|| format!("failed to select options in {}", selector))?;
+      }
+      Action::Check{selector} | Action::Uncheck{selector} => {
+        let checked = matches!(action, Action::Check{..});
+        if !actionability.force{
+          wait_for_actionable(scope, selector, None, Checks::VISIBLE, actionability).await?;
+        }
+        let element = ElementHandle::find(scope, selector).await?;
+
+        if element.is_checked().await? != checked{
+          let point = mouse::resolve_target(scope, &MouseTarget::element(selector), Checks::CLICK, actionability).await?;
+          mouse::click(page, point, MouseButton::Left, 1).await?;
+
+          if element.is_checked().await? != checked{
+            anyhow::bail!(
+              "failed to {} {}: state did not change after clicking it",
+              if checked { "check" } else { "uncheck" }, selector
+            );
+          }
+        }
+      }
+      Action::Upload{selector, files} => {
+        if !actionability.force{
+          wait_for_actionable(scope, selector, None, Checks::ATTACHED, actionability).await?;
+        }
+        scope.set_input_files(selector, files).await?;
+      }
+      Action::Press{key, repeat, selector, delay_ms} => {
+        let chords = key.chords().iter()
+          .map(|chord| KeyChord::parse(chord))
+          .collect::<Result<Vec<_>>>()?;
+
+        if let Some(selector) = selector{
+          if !actionability.force{
+            wait_for_actionable(scope, selector, None, Checks::VISIBLE, actionability).await?;
+          }
+          ElementHandle::find(scope, selector).await?.focus().await?;
+        }
+
+        for _ in 0..*repeat{
+          for chord in &chords{
+            chord.press(page).await?;
+            if let Some(delay) = delay_ms{
+              sleep(Duration::from_millis(*delay)).await;
+            }
+          }
+        }
       }
       Action::Execute{script} => {
-        page.evaluate(script.to_owned()).await?;
+        scope.evaluate(script.to_owned()).await?;
+      }
+      Action::Extract{into, selector, attribute, script} => {
+        let value = match (selector, script){
+          (Some(selector), None) => {
+            if !actionability.force{
+              wait_for_actionable(scope, selector, None, Checks::ATTACHED, actionability).await?;
+            }
+            let element = ElementHandle::find(scope, selector).await?;
+            let value = match attribute{
+              Some(attribute) => element.attribute(attribute).await?
+                .with_context(|| format!("attribute '{}' not present on {}", attribute, selector))?,
+              None => element.text().await?,
+            };
+            value.trim().to_string()
+          }
+          (None, Some(script)) => {
+            match scope.evaluate(script.to_owned()).await?.value(){
+              Some(serde_json::Value::String(value)) => value.clone(),
+              Some(serde_json::Value::Null) | None => anyhow::bail!("extract script returned no value"),
+              Some(value) => value.to_string(),
+            }
+          }
+          _ => anyhow::bail!("extract requires exactly one of selector or script"),
+        };
+        vars.set(into.clone(), value);
+      }
+      Action::Navigate{..} | Action::SwitchTab{..} | Action::WaitForPopup{..} | Action::CloseTab{..} => {
+        unreachable!("navigation and tab actions are run by execute_action")
       }
     }
     Ok(())
   }
 
-  async fn wait_for_condition(&self, page: &Page, condition: &WaitCondition) -> Result<()>{
+  async fn wait_for_condition(&self, scope: &FrameScope, network: &NetworkMonitor, condition: &WaitCondition) -> Result<()>{
     match condition{
       WaitCondition::Selector{value, timeout_ms, visible} => {
         if *visible{
-          page.wait_for_selector_visible(value, *timeout_ms).await?;
+          scope.wait_for_selector_visible(value, *timeout_ms).await?;
         }else{
-          page.find_element(value).await?;
+          let body = format!("return !!{};", query_expression(value));
+          wait_until(scope, &body, *timeout_ms, &format!("selector: {}", value)).await?;
         }
       }
       WaitCondition::Duration{ms} => {
         sleep(Duration::from_millis(*ms)).await;
       }
-      WaitCondition::NetworkIdle{timeout_ms, ..} => {
-        page.wait_for_network_idle(*timeout_ms).await?;
+      WaitCondition::NetworkIdle{timeout_ms, max_connections, idle_ms} => {
+        network.wait_for_idle(*max_connections as usize, *idle_ms, *timeout_ms).await?;
       }
-      WaitCondition::Url{pattern, timeout_ms} => {
-        let start = Instant::now();
-        loop{
-          if start.elapsed() > Duration::from_millis(*timeout_ms){
-            anyhow::bail!("timeout waiting for url pattern: {}", pattern);
-          }
-          if let Some(url) = page.url().await?{
-            if url.as_str().contains(pattern){
-              break;
-            }
-          }
-          sleep(Duration::from_millis(100)).await;
+      WaitCondition::Url{url, timeout_ms} => {
+        wait_for_url(scope.page(), url, *timeout_ms).await?;
+      }
+      WaitCondition::Element{selector, state, timeout_ms} => {
+        let check = match state{
+          ElementState::Visible | ElementState::Hidden => format!(
+            r#"
+            const visible = !!el && (() => {{
+              const rect = el.getBoundingClientRect();
+              const style = window.getComputedStyle(el);
+              return rect.height > 0 && rect.width > 0 &&
+                     style.visibility !== 'hidden' && style.display !== 'none';
+            }})();
+            return visible === {};
+            "#,
+            matches!(state, ElementState::Visible)
+          ),
+          ElementState::Enabled => "return !!el && !el.disabled;".to_string(),
+          ElementState::Disabled => "return !!el && !!el.disabled;".to_string(),
+        };
+        let body = format!("const el = {};\n{}", query_expression(selector), check);
+        let what = format!("element state: {}", state);
+        wait_until(scope, &body, *timeout_ms, &what).await?;
+      }
+      WaitCondition::Text{value, selector, absent, timeout_ms} => {
+        let text = match selector{
+          Some(selector) => format!("{}?.innerText", query_expression(selector)),
+          None => "document.body?.innerText".to_string(),
+        };
+        let body = format!("return ({} ?? '').includes({}) !== {};", text, js_string(value), absent);
+        let what = format!("text '{}' to {}", value, if *absent{"disappear"}else{"appear"});
+        wait_until(scope, &body, *timeout_ms, &what).await?;
+      }
+      WaitCondition::Function{script, timeout_ms} => {
+        let body = format!(
+          "let value = ({}); if(typeof value === 'function') value = value(); return await value;",
+          script
+        );
+        wait_until(scope, &body, *timeout_ms, "function to return true").await?;
+      }
+      WaitCondition::Count{selector, equals, min, max, timeout_ms} => {
+        let checks = [
+          equals.map(|n| format!("count === {}", n)),
+          min.map(|n| format!("count >= {}", n)),
+          max.map(|n| format!("count <= {}", n)),
+        ].into_iter().flatten().collect::<Vec<_>>();
+        if checks.is_empty(){
+          anyhow::bail!("count wait needs equals, min or max");
         }
+        let body = format!(
+          "const count = {}.length; return {};",
+          query_all_expression(selector),
+          checks.join(" && ")
+        );
+        wait_until(scope, &body, *timeout_ms, &format!("count of {}", selector)).await?;
       }
-      WaitCondition::Element{selector, state,timeout_ms} => {
-        let start = Instant::now();
-        loop{
-          if start.elapsed() > Duration::from_millis(*timeout_ms){
-            anyhow::bail!("timeout waiting for element state: {}", state);
-          }
-
-          let matches = match state{
-            ElementState::Visible => page.is_element_visible(selector).await?,
-            ElementState::Hidden => !page.is_element_visible(selector).await?,
-            ElementState::Enabled => {
-              let script = format!("!document.querySelector('{}')?.disabled", selector);
-              page.evaluate(script).await?.value().and_then(|v| v.as_bool()).unwrap_or(false)
-            }
-            ElementState::Disabled => {
-              let script = format!("document.querySelector('{}')?.disabled", selector);
-              page.evaluate(script).await?.value().and_then(|v| v.as_bool()).unwrap_or(false)
-            }
-          };
-
-          if matches{
-            break;
-          }
-          sleep(Duration::from_millis(100)).await;
+      WaitCondition::DomStable{selector, idle_ms, timeout_ms} => {
+        let root = match selector{
+          Some(selector) => query_expression(selector),
+          None => "document.documentElement".to_string(),
+        };
+        let script = format!(
+          r#"
+          new Promise((resolve) => {{
+            const root = {};
+            if(!root) return resolve(false);
+            let idle;
+            const finish = (stable) => {{
+              observer.disconnect();
+              clearTimeout(idle);
+              clearTimeout(deadline);
+              resolve(stable);
+            }};
+            const observer = new MutationObserver(() => {{
+              clearTimeout(idle);
+              idle = setTimeout(() => finish(true), {idle_ms});
+            }});
+            observer.observe(root, {{subtree: true, childList: true, attributes: true, characterData: true}});
+            idle = setTimeout(() => finish(true), {idle_ms});
+            const deadline = setTimeout(() => finish(false), {timeout_ms});
+          }})
+          "#,
+          root,
+        );
+        let stable = tokio::time::timeout(
+          Duration::from_millis(*timeout_ms + 1000),
+          scope.evaluate(script),
+        ).await;
+        let stable = match stable{
+          Ok(result) => result?.value().and_then(|v| v.as_bool()).unwrap_or(false),
+          Err(_) => false,
+        };
+        if !stable{
+          return Err(TaskError::timeout(format!(
+            "timeout after {}ms waiting for the DOM to stay unchanged for {}ms", timeout_ms, idle_ms
+          )).into());
+        }
+      }
+      WaitCondition::All{conditions} => {
+        for condition in conditions{
+          Box::pin(self.wait_for_condition(scope, network, condition)).await?;
+        }
+      }
+      WaitCondition::Any{conditions} => {
+        if conditions.is_empty(){
+          anyhow::bail!("any wait needs at least one condition");
         }
+        let waits = conditions.iter()
+          .map(|condition| Box::pin(self.wait_for_condition(scope, network, condition)))
+          .collect::<Vec<_>>();
+        futures::future::select_ok(waits).await
+          .context("none of the wait conditions was met")?;
       }
     }
     Ok(())
   }
 
-  async fn capture_state(&self, page: &Page, step_index: usize, step: &Step) -> Result<CapturedState>{
+  async fn check_assertions(
+    &self,
+    page: &Page,
+    vars: &Variables,
+    step_index: usize,
+    step: &Step,
+    options: &StepOptions<'_>,
+  ) -> Result<Vec<AssertionFailure>>{
+    let scope = frame_scope(page, vars, step.frame.as_ref(), options).await?;
+    let mut failed = Vec::new();
+    for assertion in &step.assert{
+      let assertion = assertion.render(vars)?;
+      let check = check_assertion(&scope, &assertion).await
+        .with_context(|| format!("failed to evaluate {} assertion", assertion))?;
+      if !check.passed{
+        failed.push(AssertionFailure{
+          step_index,
+          step_name: step.name.clone(),
+          kind: assertion.to_string(),
+          selector: match &assertion{
+            Assertion::Text{selector, ..} => selector.clone(),
+            Assertion::Count{selector, ..} | Assertion::Attribute{selector, ..} => Some(selector.clone()),
+            Assertion::Url{..} | Assertion::Title{..} => None,
+          },
+          expected: check.expected,
+          actual: check.actual,
+        });
+      }
+    }
+    Ok(failed)
+  }
+
+  /// captures the page with the dialog drawn over it, since chrome renders no native dialog
+  /// into screenshots. the dialog is drawn on the screenshot taken while it was open, as the
+  /// page may have moved on since it was answered; when it cannot be drawn, that screenshot is
+  /// kept as is
+  async fn capture_dialog_state(&self, tab: &Tab, step_index: usize, step: &Step, dialog: &OpenedDialog) -> Result<CapturedState>{
+    let record = &dialog.record;
+    let step_name = format!("{} ({} dialog)", step.name, record.kind);
+    let context = Some(format!("{} dialog: {}", record.kind, record.message));
+    let engine = base64::engine::general_purpose::STANDARD;
+    let screenshot = dialog.screenshot.as_ref()
+      .map(|screenshot| base64::engine::Engine::encode(&engine, screenshot));
+
+    if let Err(e) = draw_dialog(&tab.page, record, screenshot.as_deref()).await{
+      let screenshot_base64 = screenshot.with_context(|| format!("could not draw the dialog: {:#}", e))?;
+      eprintln!("failed to draw {} dialog, keeping the page without it: {:#}", record.kind, e);
+      return Ok(CapturedState{
+        step_index,
+        step_name,
+        tab: tab.index,
+        screenshot_base64,
+        url: Some(record.url.clone()),
+        has_url: !record.url.is_empty() && record.url != "about:blank",
+        viewport: extract_viewport_info(&tab.page).await?,
+        timestamp: Utc::now().to_rfc3339(),
+        context,
+        page_metadata: None,
+        dialogs: vec![record.clone()],
+      });
+    }
+
+    let state = self.capture_state(tab, step_index, &step_name, context, vec![record.clone()]).await;
+    if let Err(e) = clear_dialog(&tab.page).await{
+      eprintln!("failed to remove drawn {} dialog: {:#}", record.kind, e);
+    }
+    let mut state = state?;
+    if dialog.screenshot.is_some(){
+      // what was captured is the page behind the dialog, not the page as it is now
+      state.url = Some(record.url.clone());
+      state.has_url = !record.url.is_empty() && record.url != "about:blank";
+      state.page_metadata = None;
+    }
+    Ok(state)
+  }
+
+  async fn capture_state(
+    &self,
+    tab: &Tab,
+    step_index: usize,
+    step_name: &str,
+    context: Option<String>,
+    dialogs: Vec<DialogRecord>,
+  ) -> Result<CapturedState>{
+    let page = &tab.page;
     let options = CaptureOptions::default();
     let screenshot_bytes = capture_settled(page, 300, &options).await?;
     let engine = base64::engine::general_purpose::STANDARD;
     let screenshot_base64 = base64::engine::Engine::encode(&engine, &screenshot_bytes);
     let viewport_info = extract_viewport_info(page).await?;
-    let page_metadata = extract_page_metadata(page).await?;
+    let mut page_metadata = extract_page_metadata(page).await?;
+    let document = tab.navigation.document();
+    page_metadata.status = document.status;
+    page_metadata.redirect_chain = document.redirects;
 
     Ok(CapturedState{
       step_index,
-      step_name: step.name.clone(),
+      step_name: step_name.to_string(),
+      tab: tab.index,
       screenshot_base64,
       url: Some(page_metadata.url.clone()),
       has_url: !page_metadata.url.is_empty() && page_metadata.url != "about:blank",
       viewport: viewport_info,
       timestamp: Utc::now().to_rfc3339(),
-      context: step.description.clone(),
+      context,
       page_metadata: Some(page_metadata),
+      dialogs,
     })
   }
 }
+
+/// what a task run has produced, as reported
+fn task_result(task: &Task, progress: Progress, execution_time_ms: u64, crashes: u32) -> ExecutionResult{
+  let summary = progress.errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+  ExecutionResult{
+    task_id: task.task_def.id.clone(),
+    app: task.task_def.app.clone(),
+    description: task.task_def.description.clone(),
+    success: progress.errors.is_empty(),
+    captured_states: progress.captured_states,
+    error: (!summary.is_empty()).then(|| summary.join("; ")),
+    errors: progress.errors,
+    assertion_failures: progress.assertion_failures,
+    failures: progress.failures,
+    steps: progress.steps,
+    setup_dialogs: progress.setup_dialogs,
+    crashes,
+    execution_time_ms,
+  }
+}
+
+/// resolves once a tab of the task crashes or the connection to the browser is lost
+async fn crash(mut tabs: watch::Receiver<Option<usize>>, mut browser: watch::Receiver<bool>) -> TaskError{
+  tokio::select!{
+    Ok(tab) = tabs.wait_for(Option::is_some) => {
+      TaskError::new(ErrorKind::BrowserCrashed, format!("tab {} crashed", tab.unwrap_or_default()))
+    }
+    Ok(_) = browser.wait_for(|connected| !connected) => {
+      TaskError::new(ErrorKind::BrowserCrashed, "lost the connection to the browser")
+    }
+    else => std::future::pending().await,
+  }
+}
+
+/// the tab as it is at a failure. each part is captured on its own and left out with a warning
+/// when the page cannot give it, so a broken page still gets a report
+async fn capture_failure(tab: &Tab, step_index: Option<usize>, step_name: &str) -> FailureCapture{
+  let page = &tab.page;
+  let screenshot = best_effort("screenshot", capture_screenshot(page, &CaptureOptions::default())).await;
+  let engine = base64::engine::general_purpose::STANDARD;
+
+  FailureCapture{
+    step_index,
+    step_name: step_name.to_string(),
+    tab: tab.index,
+    url: best_effort("url", async { Ok(page.url().await?) }).await.flatten(),
+    timestamp: Utc::now().to_rfc3339(),
+    screenshot_base64: screenshot.map(|bytes| base64::engine::Engine::encode(&engine, &bytes)),
+    dom: best_effort("dom", async { Ok(page.content().await?) }).await,
+    console: tab.console.recent(),
+    requests: tab.network.recent_requests(),
+  }
+}
+
+async fn best_effort<T>(part: &str, capture: impl Future<Output = Result<T>>) -> Option<T>{
+  match timeout(FAILURE_CAPTURE_TIMEOUT, capture).await{
+    Ok(Ok(value)) => Some(value),
+    Ok(Err(e)) => {
+      eprintln!("failed to capture {} of failed page: {:#}", part, e);
+      None
+    }
+    Err(_) => {
+      eprintln!("timed out capturing {} of failed page", part);
+      None
+    }
+  }
+}
+
+/// the top document, or the frame a step or wait names once it has loaded
+async fn frame_scope(
+  page: &Page,
+  vars: &Variables,
+  frame: Option<&FrameSelector>,
+  options: &StepOptions<'_>,
+) -> Result<FrameScope>{
+  match frame{
+    Some(frame) => FrameScope::resolve(page, &frame.render(vars)?, options.actionability.timeout_ms).await,
+    None => Ok(FrameScope::main(page)),
+  }
+}
+
+/// `scheme://host[:port]` of `url`, which is how storage is keyed by origin
+fn origin_of(url: &str) -> String{
+  let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+  let host = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+  format!("{}://{}", scheme, host)
+}
+
+/// the error as reported in results: as raised where its kind was known, otherwise classified
+/// by what failed underneath it. the message keeps every context down to the root cause
+fn task_error(e: &anyhow::Error) -> TaskError{
+  if let Some(error) = e.downcast_ref::<TaskError>(){
+    return TaskError{message: format!("{:#}", e), ..error.clone()};
+  }
+  let kind = match e.downcast_ref::<CdpError>(){
+    Some(CdpError::JavascriptException(_)) => ErrorKind::ScriptError,
+    Some(CdpError::Timeout) => ErrorKind::Timeout,
+    Some(CdpError::Ws(_) | CdpError::ChannelSendError(_) | CdpError::NoResponse) => ErrorKind::BrowserCrashed,
+    _ => ErrorKind::Other,
+  };
+  TaskError::new(kind, format!("{:#}", e))
+}
@@ -1,7 +1,14 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use anyhow::Result;
+use futures::StreamExt;
 
+mod assertions;
+mod auth;
 mod browser;
 mod state_capture;
+mod variables;
 pub mod executor;
 pub mod models;
 pub mod output;
@@ -14,6 +21,9 @@ use models::task::Task;
 pub struct CaptureEngine{
   viewport_width: u32,
   viewport_height: u32,
+  concurrency: usize,
+  auth_dir: Option<PathBuf>,
+  crash_retries: u32,
 }
 
 impl Default for CaptureEngine{
@@ -27,44 +37,152 @@ impl CaptureEngine{
     Self{
       viewport_width: 1920,
       viewport_height: 1080,
+      concurrency: 1,
+      auth_dir: None,
+      crash_retries: 2,
     }
   }
 
   pub fn with_viewport(width: u32, height: u32) -> Self{
-    Self{
-      viewport_width: width,
-      viewport_height: height,
+    Self{viewport_width: width, viewport_height: height, ..Self::new()}
+  }
+
+  /// number of tasks `execute_batch` runs at once, each on its own page
+  pub fn with_concurrency(mut self, concurrency: usize) -> Self{
+    self.concurrency = concurrency.max(1);
+    self
+  }
+
+  /// directory of auth profiles (`<name>.yaml`) that tasks with `auth_required` sign in
+  /// through; saved sessions are written next to them
+  pub fn with_auth_dir(mut self, dir: impl Into<PathBuf>) -> Self{
+    self.auth_dir = Some(dir.into());
+    self
+  }
+
+  /// how many times a task is run again after the browser or one of its tabs crashed; the
+  /// browser is relaunched when it went down as a whole
+  pub fn with_crash_retries(mut self, retries: u32) -> Self{
+    self.crash_retries = retries;
+    self
+  }
+
+  async fn executor(&self) -> Result<TaskExecutor>{
+    let mut executor = TaskExecutor::new(self.viewport_width, self.viewport_height).await?;
+    executor.set_crash_retries(self.crash_retries);
+    if let Some(dir) = &self.auth_dir{
+      executor.set_auth_dir(dir.clone());
     }
+    Ok(executor)
   }
 
   pub async fn execute_task(&self, task: Task) -> Result<ExecutionResult>{
-    let executor = TaskExecutor::new(self.viewport_width, self.viewport_height).await?;
-    executor.execute(task).await
+    let executor = self.executor().await?;
+    let result = executor.execute(task).await;
+    executor.close().await?;
+    result
   }
 
+  /// runs tasks `concurrency` at a time; a task with `share_session_with` is held back until the
+  /// task it shares a session with has finished
   pub async fn execute_batch(&self, tasks: Vec<Task>) -> Result<Vec<ExecutionResult>>{
-    let executor = TaskExecutor::new(self.viewport_width, self.viewport_height).await?;
+    let mut executor = self.executor().await?;
+    executor.retain_sessions(tasks.iter().filter_map(|t| t.task_def.share_session_with.clone()));
 
+    let mut progress = BatchProgress{completed: 0, total: tasks.len()};
     let mut results = Vec::new();
-    for task in tasks{
-      match executor.execute(task).await{
-        Ok(result) => results.push(result),
-        Err(e) =>{
-          eprintln!("error executing task: {}", e);
+    let mut remaining = tasks;
+
+    while !remaining.is_empty(){
+      let pending: HashSet<String> = remaining.iter().map(|t| t.task_def.id.clone()).collect();
+      let (ready, blocked): (Vec<Task>, Vec<Task>) = remaining.into_iter().partition(|t|{
+        t.task_def.share_session_with.as_ref().is_none_or(|source| !pending.contains(source))
+      });
+
+      if ready.is_empty(){
+        for task in blocked{
+          progress.completed += 1;
+          eprintln!(
+            "{} {}: error executing task: circular share_session_with",
+            progress, task.task_def.id
+          );
         }
+        break;
       }
+
+      results.extend(self.execute_wave(&executor, ready, &mut progress).await);
+      remaining = blocked;
     }
 
+    executor.close().await?;
     Ok(results)
   }
 
+  async fn execute_wave(
+    &self,
+    executor: &TaskExecutor,
+    tasks: Vec<Task>,
+    progress: &mut BatchProgress,
+  ) -> Vec<ExecutionResult>{
+    let mut results = Vec::new();
+    let mut pending = futures::stream::iter(tasks)
+      .map(|task|{
+        let task_id = task.task_def.id.clone();
+        async move{ (task_id, executor.execute(task).await) }
+      })
+      .buffer_unordered(self.concurrency);
+
+    while let Some((task_id, outcome)) = pending.next().await{
+      progress.completed += 1;
+      match outcome{
+        Ok(result) => {
+          if result.success{
+            println!("{} {}: captured {} states", progress, task_id, result.captured_states.len());
+          }else{
+            println!(
+              "{} {}: failed: {}",
+              progress, task_id, result.error.as_deref().unwrap_or("unknown error")
+            );
+          }
+          results.push(result);
+        }
+        Err(e) =>{
+          eprintln!("{} {}: error executing task: {}", progress, task_id, e);
+        }
+      }
+    }
+
+    results
+  }
+
+  /// parses a task, resolving includes relative to the working directory
   pub fn load_task_from_yaml(yaml: &str) -> Result<Task>{
-    serde_yaml::from_str(yaml)
-      .map_err(|e| anyhow::anyhow!("failed to parse task definition: {}", e))
+    Self::parse_task(yaml, Path::new("<yaml>"))
   }
 
-  pub async fn load_task_from_file(path: &std::path::Path) -> Result<Task>{
+  /// parses a task file, resolving includes relative to it
+  pub async fn load_task_from_file(path: &Path) -> Result<Task>{
     let yaml = tokio::fs::read_to_string(path).await?;
-    Self::load_task_from_yaml(&yaml)
+    Self::parse_task(&yaml, path)
+  }
+
+  fn parse_task(yaml: &str, path: &Path) -> Result<Task>{
+    match task_definition::resolve_includes(yaml, path)?{
+      Some(document) => serde_yaml::from_value(document)
+        .map_err(|e| anyhow::anyhow!("failed to parse task definition with its includes: {}", e)),
+      None => serde_yaml::from_str(yaml)
+        .map_err(|e| anyhow::anyhow!("failed to parse task definition: {}", e)),
+    }
+  }
+}
+
+struct BatchProgress{
+  completed: usize,
+  total: usize,
+}
+
+impl fmt::Display for BatchProgress{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    write!(f, "[{}/{}]", self.completed, self.total)
   }
 }
@@ -1,10 +1,10 @@
-use std::path::PathBuf;
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
 use clap::Parser;
 
 use softlight_agent::CaptureEngine;
-use softlight_agent::models;
 use softlight_agent::output::DatasetWriter;
+use softlight_agent::task_definition;
 
 #[derive(Parser)]
 #[command(name="ui-capture")]
@@ -21,13 +21,27 @@ enum Commands{
     task: PathBuf,
     #[arg(short, long, default_value = "outputs")]
     output: PathBuf,
+    /// directory of auth profiles and their saved sessions
+    #[arg(long, default_value = "auth")]
+    auth_dir: PathBuf,
+    /// times a task is run again after the browser or one of its tabs crashed
+    #[arg(long, default_value_t = 2)]
+    crash_retries: u32,
   },
 
   Batch{
-  #[arg(short, long)]
+    #[arg(short, long)]
     tasks_dir: PathBuf,
     #[arg(short, long, default_value = "outputs")]
     output: PathBuf,
+    #[arg(short, long, default_value_t = 4)]
+    workers: usize,
+    /// directory of auth profiles and their saved sessions
+    #[arg(long, default_value = "auth")]
+    auth_dir: PathBuf,
+    /// times a task is run again after the browser or one of its tabs crashed
+    #[arg(long, default_value_t = 2)]
+    crash_retries: u32,
   },
 }
 
@@ -36,26 +50,27 @@ async fn main() -> Result<()>{
   let cli = Cli::parse();
 
   match cli.command{
-    Commands::Run{task, output} => {
-      run_single_task(&task, &output).await?;
+    Commands::Run{task, output, auth_dir, crash_retries} => {
+      run_single_task(&task, &output, auth_dir, crash_retries).await?;
     }
-    Commands::Batch{tasks_dir, output} => {
-      println!("todo...");
+    Commands::Batch{tasks_dir, output, workers, auth_dir, crash_retries} => {
+      run_batch(&tasks_dir, &output, workers, auth_dir, crash_retries).await?;
     }
   }
 
   Ok(())
 }
 
-async fn run_single_task(task_path: &PathBuf, output_dir: &PathBuf) -> Result<()>{
+async fn run_single_task(task_path: &Path, output_dir: &PathBuf, auth_dir: PathBuf, crash_retries: u32) -> Result<()>{
   println!("loading task from: {}", task_path.display());
 
-  let yaml = tokio::fs::read_to_string(task_path).await?;
-  let task: models::task::Task = serde_yaml::from_str(&yaml)?;
+  let task = CaptureEngine::load_task_from_file(task_path).await?;
 
   println!("executing task: {} ({})", task.task_def.id, task.task_def.description);
 
-  let executor = CaptureEngine::new();
+  let executor = CaptureEngine::new()
+    .with_auth_dir(auth_dir)
+    .with_crash_retries(crash_retries);
   let result = executor.execute_task(task).await?;
 
   if result.success{
@@ -70,47 +85,66 @@ async fn run_single_task(task_path: &PathBuf, output_dir: &PathBuf) -> Result<()
   Ok(())
 }
 
-// async fn run_batch(tasks_dir: &PathBuf, output_dir: &PathBuf) -> Result<()>{
-//   println!("loading tasks from: {}", tasks_dir.display());
-
-//   let mut entires = tokio::fs::read_dir(tasks_dir).await?;
-//   let mut tasks = Vec::new();
-
-//   while let Some(entry) = entires.next_entry().await?{
-//     let path = entry.path();
-//     if path.extension().and_then(|s| s.to_str()) == Some("yaml")
-//       || path.extension().and_then(|s| s.to_str()) == Some("yml"){
-//       println!("  loading: {}", path.file_name().unwrap().to_string_lossy());
-//       let yaml = tokio::fs::read_to_string(&path).await?;
-//       let task: models::task::Task = serde_yaml::from_str(&yaml)?;
-//       tasks.push(task);
-//     }
-//   }
-
-//   let executor = CaptureEngine::new();
-//   let mut results = executor.execute_batch(tasks).await?;
-
-//   for (idx, task) in tasks.into_iter().enumerate(){
-//     println!("[{}/{}] executing: {}", idx+1, results.len()+1,task.task_def.description);
-//     match executor.execute_task(task).await{
-//       Ok(result) => {
-//         if result.success{
-//           println!("  captured {} states", result.captured_states.len());
-//         }else{
-//           println!("  task failed");
-//         }
-//         results.push(result);
-//       }
-//       Err(e) => {
-//         println!("  task failed: {}", e);
-//       }
-//     }
-//     println!();
-//   }
-
-//   println!("saving results...");
-//   let write = DatasetWriter::new(output_dir);
-//   write.save_batch(results).await?;
-
-//   Ok(())
-// }
+async fn run_batch(
+  tasks_dir: &PathBuf,
+  output_dir: &PathBuf,
+  workers: usize,
+  auth_dir: PathBuf,
+  crash_retries: u32,
+) -> Result<()>{
+  println!("loading tasks from: {}", tasks_dir.display());
+
+  let mut tasks = Vec::new();
+  for path in find_task_files(tasks_dir).await?{
+    println!("  loading: {}", path.strip_prefix(tasks_dir).unwrap_or(&path).display());
+    let task = CaptureEngine::load_task_from_file(&path)
+      .await
+      .with_context(|| format!("failed to load task: {}", path.display()))?;
+    tasks.push(task);
+  }
+
+  if tasks.is_empty(){
+    anyhow::bail!("no task files found in {}", tasks_dir.display());
+  }
+
+  println!("executing {} tasks with {} workers\n", tasks.len(), workers);
+  let executor = CaptureEngine::new()
+    .with_concurrency(workers)
+    .with_auth_dir(auth_dir)
+    .with_crash_retries(crash_retries);
+  let results = executor.execute_batch(tasks).await?;
+
+  println!("\nsaving results...");
+  let writer = DatasetWriter::new(output_dir);
+  writer.save_batch(results).await?;
+
+  Ok(())
+}
+
+async fn find_task_files(dir: &Path) -> Result<Vec<PathBuf>>{
+  let mut files = Vec::new();
+  let mut dirs = vec![dir.to_path_buf()];
+
+  while let Some(dir) = dirs.pop(){
+    let mut entries = tokio::fs::read_dir(&dir)
+      .await
+      .with_context(|| format!("failed to read directory: {}", dir.display()))?;
+
+    while let Some(entry) = entries.next_entry().await?{
+      let path = entry.path();
+      if entry.file_type().await?.is_dir(){
+        dirs.push(path);
+      }else if matches!(path.extension().and_then(|s| s.to_str()), Some("yaml" | "yml")){
+        // step fragments live alongside tasks but only run through an include
+        let yaml = tokio::fs::read_to_string(&path).await
+          .with_context(|| format!("failed to read {}", path.display()))?;
+        if task_definition::is_task_document(&yaml){
+          files.push(path);
+        }
+      }
+    }
+  }
+
+  files.sort();
+  Ok(files)
+}
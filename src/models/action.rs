@@ -1,14 +1,56 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::models::key_sequence::KeySequence;
+use crate::models::mouse_button::MouseButton;
+use crate::models::mouse_target::MouseTarget;
+use crate::models::navigation::NavigationOptions;
 use crate::models::scroll_direction::ScrollDirection;
+use crate::models::tab_selector::TabSelector;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Action{
-  Navigate{url: String},
+  Navigate{
+    url: String,
+    #[serde(flatten)]
+    navigation: NavigationOptions,
+  },
   Click{
-    selector: String,
+    #[serde(flatten)]
+    target: MouseTarget,
     #[serde(default)]
     wait_before_ms: Option<u64>,
+    #[serde(default)]
+    button: MouseButton,
+    #[serde(default = "default_click_count")]
+    click_count: u32,
+  },
+  DoubleClick{
+    #[serde(flatten)]
+    target: MouseTarget,
+  },
+  RightClick{
+    #[serde(flatten)]
+    target: MouseTarget,
+  },
+  /// presses a button without releasing it, for long-press and hold states
+  MouseDown{
+    #[serde(flatten)]
+    target: MouseTarget,
+    #[serde(default)]
+    button: MouseButton,
+  },
+  MouseUp{
+    #[serde(flatten)]
+    target: MouseTarget,
+    #[serde(default)]
+    button: MouseButton,
+  },
+  Drag{
+    from: MouseTarget,
+    to: MouseTarget,
+    /// number of intermediate mouse moves between the two points
+    #[serde(default = "default_drag_steps")]
+    steps: u32,
   },
   Type{
     selector: String,
@@ -22,8 +64,80 @@ pub enum Action{
     amount: i32,
   },
   Hover{selector: String},
-  Press{key: String},
+  /// selects options of a `<select>` by value, label or index; several only on multi-selects
+  Select{
+    selector: String,
+    #[serde(default, deserialize_with = "one_or_many")]
+    value: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    label: Vec<String>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    index: Vec<usize>,
+  },
+  Check{selector: String},
+  Uncheck{selector: String},
+  Upload{
+    selector: String,
+    #[serde(deserialize_with = "one_or_many")]
+    files: Vec<String>,
+  },
+  Press{
+    key: KeySequence,
+    #[serde(default = "default_repeat")]
+    repeat: u32,
+    #[serde(default)]
+    selector: Option<String>,
+    #[serde(default)]
+    delay_ms: Option<u64>,
+  },
   Execute{script: String},
+  /// stores the text or an attribute of `selector`, or the result of `script`, in variable `into`
+  Extract{
+    into: String,
+    #[serde(default)]
+    selector: Option<String>,
+    #[serde(default)]
+    attribute: Option<String>,
+    #[serde(default)]
+    script: Option<String>,
+  },
+  /// makes another tab the one later steps act on and capture from
+  SwitchTab{
+    #[serde(flatten)]
+    tab: TabSelector,
+  },
+  /// waits for a tab opened by the page (`target=_blank`, `window.open`) and switches to it
+  WaitForPopup{
+    #[serde(default = "default_popup_timeout")]
+    timeout_ms: u64,
+  },
+  /// closes the given tab, or the active one, returning to the previously active tab
+  CloseTab{
+    #[serde(default)]
+    index: Option<usize>,
+  },
 }
 
 fn default_clear() -> bool{true}
+fn default_repeat() -> u32{1}
+fn default_click_count() -> u32{1}
+fn default_drag_steps() -> u32{10}
+fn default_popup_timeout() -> u64{10000}
+
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+  D: Deserializer<'de>,
+  T: Deserialize<'de>,
+{
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum OneOrMany<T>{
+    One(T),
+    Many(Vec<T>),
+  }
+
+  Ok(match OneOrMany::deserialize(deserializer)?{
+    OneOrMany::One(value) => vec![value],
+    OneOrMany::Many(values) => values,
+  })
+}
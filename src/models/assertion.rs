@@ -0,0 +1,47 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::models::url_pattern::UrlPattern;
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion{
+  /// page text, or the text of `selector`, contains `value`
+  Text{
+    value: String,
+    #[serde(default)]
+    selector: Option<String>,
+  },
+  Count{
+    selector: String,
+    #[serde(default)]
+    equals: Option<usize>,
+    #[serde(default)]
+    min: Option<usize>,
+    #[serde(default)]
+    max: Option<usize>,
+  },
+  Attribute{
+    selector: String,
+    name: String,
+    value: String,
+  },
+  /// current url matches, the way a url wait does
+  Url{
+    #[serde(flatten)]
+    url: UrlPattern,
+  },
+  /// document title contains `value`
+  Title{value: String},
+}
+
+impl fmt::Display for Assertion{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match self{
+      Assertion::Text{..} => write!(f, "text"),
+      Assertion::Count{..} => write!(f, "count"),
+      Assertion::Attribute{..} => write!(f, "attribute"),
+      Assertion::Url{..} => write!(f, "url"),
+      Assertion::Title{..} => write!(f, "title"),
+    }
+  }
+}
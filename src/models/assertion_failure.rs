@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AssertionFailure{
+  pub step_index: usize,
+  pub step_name: String,
+  pub kind: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub selector: Option<String>,
+  pub expected: String,
+  pub actual: String,
+}
@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::models::assertion::Assertion;
+use crate::models::step::Step;
+
+/// how to sign in to an app, loaded from `<auth dir>/<name>.yaml`. the login steps run once and
+/// the resulting session is saved next to the profile as `<name>.state.json` for every task
+/// with `auth_required` to reuse
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AuthProfile{
+  pub base_url: String,
+  #[serde(default)]
+  pub vars: HashMap<String, String>,
+  pub login: Vec<Step>,
+  /// tells whether a saved session is still signed in; without it a saved session is trusted
+  #[serde(default)]
+  pub check: Option<SessionCheck>,
+  #[serde(default = "default_actionability_timeout")]
+  pub actionability_timeout_ms: u64,
+  /// how long one attempt of a login step may take
+  #[serde(default = "default_step_timeout")]
+  pub step_timeout_ms: u64,
+}
+
+/// assertions that hold on `url` only while signed in; a failing one triggers a new login
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionCheck{
+  pub url: String,
+  pub assert: Vec<Assertion>,
+}
+
+fn default_actionability_timeout() -> u64 {5000}
+fn default_step_timeout() -> u64 {60000}
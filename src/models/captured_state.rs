@@ -1,5 +1,6 @@
 use serde::Serialize;
 use crate::models::{
+  dialog_record::DialogRecord,
   metadata::PageMetadata,
   viewport_info::ViewportInfo
 };
@@ -8,6 +9,8 @@ use crate::models::{
 pub struct CapturedState{
   pub step_index: usize,
   pub step_name: String,
+  /// the task tab the state was captured from, in the order tabs were opened
+  pub tab: usize,
   pub screenshot_base64: String,
   pub url: Option<String>,
   pub has_url: bool,
@@ -16,4 +19,7 @@ pub struct CapturedState{
   pub context: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub page_metadata: Option<PageMetadata>,
+  /// dialogs the page opened during the step
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub dialogs: Vec<DialogRecord>,
 }
@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+/// a message the page logged to its console, or an exception it did not catch
+#[derive(Debug, Serialize, Clone)]
+pub struct ConsoleMessage{
+  /// `log`, `warning`, `error` and the other console methods, or `exception`
+  pub level: String,
+  pub text: String,
+  pub timestamp: String,
+}
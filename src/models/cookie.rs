@@ -1,8 +1,35 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// a cookie to set before the task starts. it needs a `domain` or a `url`; when only the url
+/// is given, domain, path and secure are derived from it the way the browser would
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Cookie{
   pub name: String,
   pub value: String,
-  pub domain: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub domain: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub url: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub path: Option<String>,
+  /// unix seconds; without it the cookie lasts for the session
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub expires: Option<f64>,
+  #[serde(default, alias = "httpOnly")]
+  pub http_only: bool,
+  #[serde(default)]
+  pub secure: bool,
+  #[serde(default, alias = "sameSite", skip_serializing_if = "Option::is_none")]
+  pub same_site: Option<SameSite>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SameSite{
+  #[serde(alias = "Strict")]
+  Strict,
+  #[serde(alias = "Lax")]
+  Lax,
+  #[serde(alias = "None")]
+  None,
 }
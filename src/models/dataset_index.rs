@@ -7,5 +7,7 @@ pub struct DatasetIndex{
   pub total_tasks: usize,
   pub successful_tasks: usize,
   pub total_states: usize,
+  /// browser and tab crashes across all tasks
+  pub total_crashes: u32,
   pub tasks: Vec<TaskSummary>,
 }
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogAction{
+  #[default]
+  Accept,
+  Dismiss,
+  /// accepts, entering `text` into a `prompt`
+  Respond,
+}
+
+/// how `alert`, `confirm`, `prompt` and `beforeunload` dialogs are answered
+#[derive(Debug, Deserialize, Clone, Default, Serialize)]
+pub struct DialogPolicy{
+  #[serde(default)]
+  pub action: DialogAction,
+  #[serde(default)]
+  pub text: Option<String>,
+  /// captures an extra state per dialog, with the dialog drawn over the page as it was when
+  /// the dialog opened
+  #[serde(default)]
+  pub capture: bool,
+}
@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+/// a javascript dialog the page opened and how it was answered
+#[derive(Debug, Serialize, Clone)]
+pub struct DialogRecord{
+  pub kind: String,
+  pub message: String,
+  pub url: String,
+  pub accepted: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub prompt_text: Option<String>,
+}
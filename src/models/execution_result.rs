@@ -1,5 +1,10 @@
 use serde::Serialize;
+use crate::models::assertion_failure::AssertionFailure;
 use crate::models::captured_state::CapturedState;
+use crate::models::dialog_record::DialogRecord;
+use crate::models::failure_capture::FailureCapture;
+use crate::models::step_result::StepResult;
+use crate::models::task_error::TaskError;
 
 #[derive(Debug, Serialize)]
 pub struct ExecutionResult{
@@ -8,6 +13,17 @@ pub struct ExecutionResult{
   pub description: String,
   pub success: bool,
   pub captured_states: Vec<CapturedState>,
+  /// every failure, joined into one line
   pub error: Option<String>,
+  pub errors: Vec<TaskError>,
+  pub assertion_failures: Vec<AssertionFailure>,
+  /// the page as it was at each failure
+  pub failures: Vec<FailureCapture>,
+  pub steps: Vec<StepResult>,
+  /// dialogs opened while signing in or opening the starting url, before any step ran
+  pub setup_dialogs: Vec<DialogRecord>,
+  /// times the browser or one of the task's tabs crashed while it ran. the task is run again
+  /// from the start after each crash, up to the configured number of retries
+  pub crashes: u32,
   pub execution_time_ms: u64,
 }
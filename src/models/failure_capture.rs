@@ -0,0 +1,20 @@
+use serde::Serialize;
+use crate::models::{console_message::ConsoleMessage, network_request::NetworkRequest};
+
+/// the active tab as it was when a step or the setup failed. every part is captured on a best
+/// effort basis, since a failing page may not answer
+#[derive(Debug, Serialize)]
+pub struct FailureCapture{
+  /// the failed step, or none when the setup failed
+  pub step_index: Option<usize>,
+  pub step_name: String,
+  pub tab: usize,
+  pub url: Option<String>,
+  pub timestamp: String,
+  pub screenshot_base64: Option<String>,
+  /// the document serialized as html
+  pub dom: Option<String>,
+  pub console: Vec<ConsoleMessage>,
+  /// the most recent requests of the tab, oldest first
+  pub requests: Vec<NetworkRequest>,
+}
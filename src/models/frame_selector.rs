@@ -0,0 +1,25 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+/// picks a frame by the selector of its `<iframe>` element in the top document, by its
+/// `name`, or by a substring of its url; the first of these that is set is used
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct FrameSelector{
+  #[serde(default)]
+  pub selector: Option<String>,
+  #[serde(default)]
+  pub name: Option<String>,
+  #[serde(default)]
+  pub url: Option<String>,
+}
+
+impl fmt::Display for FrameSelector{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match (&self.selector, &self.name, &self.url){
+      (Some(selector), _, _) => write!(f, "frame {}", selector),
+      (None, Some(name), _) => write!(f, "frame named '{}'", name),
+      (None, None, Some(url)) => write!(f, "frame with url '{}'", url),
+      (None, None, None) => write!(f, "<no frame>"),
+    }
+  }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// a single key chord like `Control+Shift+P` or a list of chords pressed in order
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(untagged)]
+pub enum KeySequence{
+  Single(String),
+  Sequence(Vec<String>),
+}
+
+impl KeySequence{
+  pub fn chords(&self) -> &[String]{
+    match self{
+      KeySequence::Single(chord) => std::slice::from_ref(chord),
+      KeySequence::Sequence(chords) => chords,
+    }
+  }
+}
@@ -1,4 +1,11 @@
 use serde::{Deserialize, Serialize};
+use crate::models::assertion_failure::AssertionFailure;
+use crate::models::console_message::ConsoleMessage;
+use crate::models::dialog_record::DialogRecord;
+use crate::models::navigation::Redirect;
+use crate::models::network_request::NetworkRequest;
+use crate::models::step_result::StepResult;
+use crate::models::task_error::TaskError;
 use crate::models::viewport_info::ViewportInfo;
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
@@ -23,6 +30,12 @@ pub struct PageMetadata{
   pub active_element: Option<String>,
   pub has_modals: bool,
   pub has_overlays: bool,
+  /// http status the document was served with
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub status: Option<i64>,
+  /// redirects followed before the document was served, oldest first
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub redirect_chain: Vec<Redirect>,
 }
 
 #[derive(Debug, Serialize)]
@@ -34,17 +47,61 @@ pub struct TaskMetadata{
   pub execution_time_ms: u64,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub error: Option<String>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub errors: Vec<TaskError>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub assertion_failures: Vec<AssertionFailure>,
+  pub steps: Vec<StepResult>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub setup_dialogs: Vec<DialogRecord>,
   pub states: Vec<StateMetadata>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub failures: Vec<FailureMetadata>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct StateMetadata{
   pub step_index: usize,
   pub step_name: String,
+  pub tab: usize,
   pub filename: String,
   pub url: Option<String>,
   pub has_url: bool,
   pub viewport: ViewportInfo,
+  /// http status the document was served with
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub status: Option<i64>,
+  /// redirects followed before the document was served, oldest first
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub redirect_chain: Vec<Redirect>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub context: Option<String>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub dialogs: Vec<DialogRecord>,
+}
+
+/// a failure capture, with its files given relative to the task directory
+#[derive(Debug, Serialize)]
+pub struct FailureMetadata{
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub step_index: Option<usize>,
+  pub step_name: String,
+  pub tab: usize,
+  pub url: Option<String>,
+  pub timestamp: String,
+  /// the screenshot, when the page could still be captured
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub screenshot: Option<String>,
+  /// the serialized dom, when the page could still be captured
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub dom: Option<String>,
+  /// the console messages and network requests leading up to the failure
+  pub activity: String,
+}
+
+/// the console and network activity of a page up to a failure
+#[derive(Debug, Serialize)]
+pub struct FailureActivity<'a>{
+  pub console: &'a [ConsoleMessage],
+  pub requests: &'a [NetworkRequest],
 }
@@ -1,13 +1,34 @@
 pub mod action;
+pub mod assertion;
+pub mod assertion_failure;
+pub mod auth_profile;
 pub mod captured_state;
+pub mod console_message;
 pub mod cookie;
 pub mod dataset_index;
+pub mod dialog_policy;
+pub mod dialog_record;
 pub mod element_state;
+pub mod failure_capture;
 pub mod execution_result;
+pub mod frame_selector;
+pub mod key_sequence;
 pub mod metadata;
+pub mod mouse_button;
+pub mod mouse_target;
+pub mod navigation;
+pub mod network_request;
+pub mod point;
+pub mod retry_policy;
 pub mod scroll_direction;
 pub mod setup;
 pub mod step;
+pub mod step_result;
+pub mod step_wait;
+pub mod storage_state;
+pub mod tab_selector;
+pub mod url_pattern;
 pub mod task;
+pub mod task_error;
 pub mod viewport_info;
 pub mod wait_condition;
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseButton{
+  #[default]
+  Left,
+  Middle,
+  Right,
+}
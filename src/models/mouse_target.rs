@@ -0,0 +1,33 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::models::point::Point;
+
+/// where a mouse action lands: the center of `selector`, `position` relative to the top-left
+/// corner of `selector`, or `position` in viewport coordinates when there is no selector
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct MouseTarget{
+  #[serde(default)]
+  pub selector: Option<String>,
+  #[serde(default)]
+  pub position: Option<Point>,
+}
+
+impl MouseTarget{
+  pub fn element(selector: &str) -> Self{
+    Self{
+      selector: Some(selector.to_string()),
+      position: None,
+    }
+  }
+}
+
+impl fmt::Display for MouseTarget{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match (&self.selector, &self.position){
+      (Some(selector), Some(p)) => write!(f, "{} at ({}, {})", selector, p.x, p.y),
+      (Some(selector), None) => write!(f, "{}", selector),
+      (None, Some(p)) => write!(f, "({}, {})", p.x, p.y),
+      (None, None) => write!(f, "<no target>"),
+    }
+  }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+/// how a navigation is awaited and which responses fail it
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct NavigationOptions{
+  #[serde(default)]
+  pub wait_until: WaitUntil,
+  #[serde(default = "default_timeout")]
+  pub timeout_ms: u64,
+  /// statuses of the final document response that fail the navigation, such as `4xx`, `404`
+  /// or `500-599`
+  #[serde(default)]
+  pub fail_on_status: Vec<StatusRange>,
+}
+
+impl Default for NavigationOptions{
+  fn default() -> Self{
+    Self{
+      wait_until: WaitUntil::default(),
+      timeout_ms: default_timeout(),
+      fail_on_status: Vec::new(),
+    }
+  }
+}
+
+/// the point a navigation counts as done: once the response is in (`commit`), the document is
+/// parsed (`domcontentloaded`), it and its resources have loaded (`load`), or no request has
+/// been made for 500ms after that (`networkidle`)
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WaitUntil{
+  Commit,
+  #[serde(rename = "domcontentloaded")]
+  DomContentLoaded,
+  #[default]
+  Load,
+  #[serde(rename = "networkidle")]
+  NetworkIdle,
+}
+
+impl fmt::Display for WaitUntil{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match self{
+      WaitUntil::Commit => write!(f, "commit"),
+      WaitUntil::DomContentLoaded => write!(f, "domcontentloaded"),
+      WaitUntil::Load => write!(f, "load"),
+      WaitUntil::NetworkIdle => write!(f, "networkidle"),
+    }
+  }
+}
+
+/// an inclusive range of http statuses, written as one status, `4xx` or `400-499`
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Serialize)]
+#[serde(try_from = "StatusSpec", into = "String")]
+pub struct StatusRange{
+  pub min: i64,
+  pub max: i64,
+}
+
+impl StatusRange{
+  pub fn contains(&self, status: i64) -> bool{
+    (self.min..=self.max).contains(&status)
+  }
+}
+
+/// a single status may be written as a plain number
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatusSpec{
+  Status(i64),
+  Range(String),
+}
+
+impl TryFrom<StatusSpec> for StatusRange{
+  type Error = String;
+
+  fn try_from(spec: StatusSpec) -> Result<Self, Self::Error>{
+    let value = match spec{
+      StatusSpec::Status(status) => return Ok(Self{min: status, max: status}),
+      StatusSpec::Range(value) => value,
+    };
+    let invalid = || format!("invalid status range '{}', expected e.g. 404, 4xx or 400-499", value);
+    let trimmed = value.trim();
+
+    if let Some(class) = trimmed.strip_suffix("xx").or_else(|| trimmed.strip_suffix("XX")){
+      let class: i64 = class.parse().map_err(|_| invalid())?;
+      if !(1..=5).contains(&class){
+        return Err(invalid());
+      }
+      return Ok(Self{min: class * 100, max: class * 100 + 99});
+    }
+    if let Some((min, max)) = trimmed.split_once('-'){
+      let min = min.trim().parse().map_err(|_| invalid())?;
+      let max = max.trim().parse().map_err(|_| invalid())?;
+      if min > max{
+        return Err(invalid());
+      }
+      return Ok(Self{min, max});
+    }
+    let status = trimmed.parse().map_err(|_| invalid())?;
+    Ok(Self{min: status, max: status})
+  }
+}
+
+impl From<StatusRange> for String{
+  fn from(range: StatusRange) -> Self{
+    range.to_string()
+  }
+}
+
+impl fmt::Display for StatusRange{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    if self.min == self.max{
+      write!(f, "{}", self.min)
+    }else if self.min % 100 == 0 && self.max == self.min + 99{
+      write!(f, "{}xx", self.min / 100)
+    }else{
+      write!(f, "{}-{}", self.min, self.max)
+    }
+  }
+}
+
+/// one hop of a redirect chain: the url that answered with a redirect, and its status
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct Redirect{
+  pub url: String,
+  pub status: i64,
+}
+
+fn default_timeout() -> u64 {30000}
+
+#[cfg(test)]
+mod tests{
+  use super::*;
+
+  fn parse(spec: &str) -> Result<StatusRange, String>{
+    StatusRange::try_from(StatusSpec::Range(spec.to_string()))
+  }
+
+  #[test]
+  fn classes_span_their_hundred(){
+    assert_eq!(parse("4xx"), Ok(StatusRange{min: 400, max: 499}));
+    assert_eq!(parse(" 5XX "), Ok(StatusRange{min: 500, max: 599}));
+    assert!(parse("6xx").is_err());
+    assert!(parse("0xx").is_err());
+    assert!(parse("xx").is_err());
+  }
+
+  #[test]
+  fn ranges_are_inclusive_and_ordered(){
+    let range = parse("400 - 403").unwrap();
+    assert_eq!(range, StatusRange{min: 400, max: 403});
+    assert!(range.contains(400) && range.contains(403));
+    assert!(!range.contains(404));
+    assert!(parse("499-400").is_err());
+    assert!(parse("400-").is_err());
+  }
+
+  #[test]
+  fn single_statuses_parse_from_strings_and_numbers(){
+    assert_eq!(parse("404"), Ok(StatusRange{min: 404, max: 404}));
+    assert_eq!(StatusRange::try_from(StatusSpec::Status(503)), Ok(StatusRange{min: 503, max: 503}));
+    assert!(parse("not found").is_err());
+  }
+
+  #[test]
+  fn ranges_print_as_they_are_written(){
+    for (spec, printed) in [("404", "404"), ("4xx", "4xx"), ("400-403", "400-403"), ("400-499", "4xx")]{
+      assert_eq!(parse(spec).unwrap().to_string(), printed);
+    }
+  }
+
+  #[test]
+  fn fail_on_status_accepts_mixed_specs(){
+    let options: NavigationOptions = serde_yaml::from_str("fail_on_status: [404, 5xx, 401-403]").unwrap();
+    assert_eq!(options.fail_on_status, [
+      StatusRange{min: 404, max: 404},
+      StatusRange{min: 500, max: 599},
+      StatusRange{min: 401, max: 403},
+    ]);
+    assert_eq!(options.wait_until, WaitUntil::Load);
+  }
+}
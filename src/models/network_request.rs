@@ -0,0 +1,16 @@
+use serde::Serialize;
+
+/// a request the page made and how it ended, if it has
+#[derive(Debug, Serialize, Clone)]
+pub struct NetworkRequest{
+  pub method: String,
+  pub url: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub resource_type: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub status: Option<i64>,
+  /// why the request failed, such as `net::ERR_CONNECTION_REFUSED`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+  pub finished: bool,
+}
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Clone, Copy, Serialize)]
+pub struct Point{
+  pub x: f64,
+  pub y: f64,
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use crate::models::task_error::ErrorKind;
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct RetryPolicy{
+  /// total number of attempts, including the first one
+  #[serde(default = "default_attempts")]
+  pub attempts: u32,
+  /// delay before the second attempt
+  #[serde(default = "default_backoff_ms")]
+  pub backoff_ms: u64,
+  /// multiplier applied to the delay after every further attempt
+  #[serde(default = "default_backoff_factor")]
+  pub backoff_factor: f64,
+  /// error kinds that are retried; empty retries everything
+  #[serde(default)]
+  pub retry_on: Vec<RetryableError>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryableError{
+  ElementNotFound,
+  Timeout,
+  Navigation,
+  Script,
+}
+
+impl RetryPolicy{
+  pub fn delay(&self, attempt: u32) -> Duration{
+    let factor = self.backoff_factor.max(1.0).powi(attempt.saturating_sub(1) as i32);
+    Duration::from_millis((self.backoff_ms as f64 * factor) as u64)
+  }
+
+  /// whether a failure of `kind` is retried
+  pub fn is_retryable(&self, kind: ErrorKind) -> bool{
+    self.retry_on.is_empty() || self.retry_on.iter().any(|retryable| retryable.matches(kind))
+  }
+}
+
+impl RetryableError{
+  fn matches(&self, kind: ErrorKind) -> bool{
+    kind == match self{
+      RetryableError::ElementNotFound => ErrorKind::SelectorNotFound,
+      RetryableError::Timeout => ErrorKind::Timeout,
+      RetryableError::Navigation => ErrorKind::NavigationFailed,
+      RetryableError::Script => ErrorKind::ScriptError,
+    }
+  }
+}
+
+fn default_attempts() -> u32 {3}
+fn default_backoff_ms() -> u64 {500}
+fn default_backoff_factor() -> f64 {2.0}
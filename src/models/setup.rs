@@ -2,13 +2,26 @@ use std::collections::HashMap;
 use serde::Deserialize;
 use serde::Serialize;
 use crate::models::cookie::Cookie;
+use crate::models::navigation::NavigationOptions;
+use crate::models::storage_state::OriginStorage;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Setup {
+  /// starts the task signed in, through the auth profile named by `auth_profile` or the app
   pub auth_required: bool,
+  #[serde(default)]
+  pub auth_profile: Option<String>,
   pub starting_url: Option<String>,
+  /// how the starting url is awaited and which of its responses fail the setup, so a task that
+  /// lands on an error or sign-in page stops before its first step
+  #[serde(default)]
+  pub navigation: NavigationOptions,
   #[serde(default)]
   pub cookies: Option<Vec<Cookie>>,
+  /// localStorage for the origin of the task's `base_url`
   #[serde(default)]
   pub local_storage: Option<HashMap<String, String>>,
+  /// local, session and IndexedDB storage per origin, in place before the first navigation
+  #[serde(default)]
+  pub storage: Vec<OriginStorage>,
 }
@@ -1,15 +1,43 @@
 use serde::{Deserialize, Serialize};
 use crate::models::action::Action;
-use crate::models::wait_condition::WaitCondition;
+use crate::models::assertion::Assertion;
+use crate::models::dialog_policy::DialogPolicy;
+use crate::models::retry_policy::RetryPolicy;
+use crate::models::frame_selector::FrameSelector;
+use crate::models::step_wait::StepWait;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Step {
   pub name: String,
   pub action: Action,
+  /// runs the action, wait and assertions inside this frame instead of the top document
   #[serde(default)]
-  pub wait: Option<WaitCondition>,
+  pub frame: Option<FrameSelector>,
+  #[serde(default)]
+  pub wait: Option<StepWait>,
+  /// checked after the wait; any failure fails the step before it is captured
+  #[serde(default)]
+  pub assert: Vec<Assertion>,
   #[serde(default)]
   pub capture: bool,
   #[serde(default)]
   pub description: Option<String>,
+  /// overrides the task-level `step_timeout_ms` for this step
+  #[serde(default)]
+  pub timeout_ms: Option<u64>,
+  /// overrides the task-level retry policy for this step
+  #[serde(default)]
+  pub retry: Option<RetryPolicy>,
+  /// acts on elements without waiting for them to be visible, stable, enabled and unobscured
+  #[serde(default)]
+  pub force: bool,
+  /// a failure skips the step without failing the task
+  #[serde(default)]
+  pub optional: bool,
+  /// a failure fails the task but the remaining steps still run
+  #[serde(default)]
+  pub continue_on_error: bool,
+  /// overrides the task-level dialog policy for this step
+  #[serde(default)]
+  pub dialogs: Option<DialogPolicy>,
 }
@@ -0,0 +1,22 @@
+use serde::Serialize;
+use crate::models::dialog_record::DialogRecord;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus{
+  Succeeded,
+  Failed,
+  Skipped,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct StepResult{
+  pub step_index: usize,
+  pub step_name: String,
+  pub status: StepStatus,
+  pub attempts: u32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub dialogs: Vec<DialogRecord>,
+}
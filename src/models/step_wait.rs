@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+use crate::models::frame_selector::FrameSelector;
+use crate::models::wait_condition::WaitCondition;
+
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct StepWait{
+  #[serde(flatten)]
+  pub condition: WaitCondition,
+  /// frame to wait in when it differs from the step's frame
+  #[serde(default)]
+  pub frame: Option<FrameSelector>,
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::models::cookie::Cookie;
+
+/// cookies and web storage of a signed-in browser context, saved so later tasks can start
+/// from it instead of logging in again
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Serialize)]
+pub struct StorageState{
+  #[serde(default)]
+  pub cookies: Vec<Cookie>,
+  #[serde(default)]
+  pub origins: Vec<OriginStorage>,
+}
+
+/// storage to seed for one origin, such as `https://app.example.com`
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct OriginStorage{
+  pub origin: String,
+  #[serde(default)]
+  pub local_storage: HashMap<String, String>,
+  #[serde(default)]
+  pub session_storage: HashMap<String, String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub indexed_db: Vec<IndexedDbSeed>,
+}
+
+/// an IndexedDB database created with its object stores and records when the origin does not
+/// have it yet. `version` should match the one the app opens it with
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct IndexedDbSeed{
+  pub name: String,
+  #[serde(default = "default_version")]
+  pub version: u64,
+  pub stores: Vec<ObjectStoreSeed>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct ObjectStoreSeed{
+  pub name: String,
+  #[serde(default)]
+  pub key_path: Option<String>,
+  #[serde(default)]
+  pub auto_increment: bool,
+  #[serde(default)]
+  pub records: Vec<IndexedDbRecord>,
+}
+
+/// a record and, for stores without a key path, its key
+#[derive(Debug, Deserialize, Clone, PartialEq, Serialize)]
+pub struct IndexedDbRecord{
+  #[serde(default)]
+  pub key: Option<serde_json::Value>,
+  pub value: serde_json::Value,
+}
+
+fn default_version() -> u64 {1}
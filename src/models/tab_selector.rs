@@ -0,0 +1,25 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+/// picks one of the task's open tabs by the order it was opened in (the first tab is 0), by
+/// a substring of its url, or the most recently opened one
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct TabSelector{
+  #[serde(default)]
+  pub index: Option<usize>,
+  #[serde(default)]
+  pub url: Option<String>,
+  #[serde(default)]
+  pub latest: bool,
+}
+
+impl fmt::Display for TabSelector{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match (&self.index, &self.url, self.latest){
+      (Some(index), _, _) => write!(f, "tab {}", index),
+      (None, Some(url), _) => write!(f, "tab with url '{}'", url),
+      (None, None, true) => write!(f, "latest tab"),
+      (None, None, false) => write!(f, "<no tab>"),
+    }
+  }
+}
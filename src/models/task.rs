@@ -1,6 +1,9 @@
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use crate::models::captured_state::CapturedState;
+use crate::models::dialog_policy::DialogPolicy;
 use crate::models::metadata::Metadata;
+use crate::models::retry_policy::RetryPolicy;
 use crate::models::setup::Setup;
 use crate::models::step::Step;
 
@@ -17,10 +20,38 @@ pub struct TaskDefinition{
   pub app: String,
   pub description: String,
   pub base_url: String,
+  /// values for `${name}` placeholders in step fields
+  #[serde(default)]
+  pub vars: HashMap<String, String>,
+  /// id of a task in the same batch whose browser context (cookies, storage, cache) this task
+  /// reuses instead of starting from a fresh one; that task runs first
+  #[serde(default)]
+  pub share_session_with: Option<String>,
   pub setup: Option<Setup>,
+  /// how long an action waits for its target element to become actionable
+  #[serde(default = "default_actionability_timeout")]
+  pub actionability_timeout_ms: u64,
+  /// how long one attempt of a step's action and wait may take, for steps that do not set
+  /// their own `timeout_ms`
+  #[serde(default = "default_step_timeout")]
+  pub step_timeout_ms: u64,
+  /// wall-clock budget for the whole task, setup included. a task that runs out is stopped
+  /// and reports the steps and states it got through
+  #[serde(default)]
+  pub timeout_ms: Option<u64>,
+  /// retry policy for every step that does not set its own
+  #[serde(default)]
+  pub retry: Option<RetryPolicy>,
+  /// how javascript dialogs are answered during setup and in steps that do not set their own
+  /// policy; they are accepted when neither sets one
+  #[serde(default)]
+  pub dialogs: Option<DialogPolicy>,
   pub steps: Vec<Step>,
 }
 
+fn default_actionability_timeout() -> u64 {5000}
+fn default_step_timeout() -> u64 {60000}
+
 #[derive(Debug, Serialize)]
 pub struct TaskOutput{
   pub task_id: String,
@@ -38,5 +69,7 @@ pub struct TaskSummary{
   pub description: String,
   pub success: bool,
   pub state_count: usize,
+  /// times the task was interrupted by a crash
+  pub crashes: u32,
   pub path: String,
 }
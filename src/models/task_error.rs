@@ -0,0 +1,95 @@
+use std::fmt;
+use serde::Serialize;
+
+/// what went wrong, coarse enough to group failures across many runs by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind{
+  SelectorNotFound,
+  Timeout,
+  NavigationFailed,
+  ScriptError,
+  BrowserCrashed,
+  AssertionFailed,
+  /// signing in, seeding cookies or storage, or opening the starting url failed
+  SetupFailed,
+  Other,
+}
+
+/// a failure of a task, with where it happened. raised as is where the kind is known, such as
+/// a selector lookup, and completed with the step and page url by the executor
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskError{
+  pub kind: ErrorKind,
+  pub message: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub step_index: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub step_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub selector: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub url: Option<String>,
+}
+
+impl TaskError{
+  pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self{
+    Self{
+      kind,
+      message: message.into(),
+      step_index: None,
+      step_name: None,
+      selector: None,
+      url: None,
+    }
+  }
+
+  pub fn selector_not_found(selector: &str) -> Self{
+    Self::new(ErrorKind::SelectorNotFound, format!("element not found: {}", selector))
+      .with_selector(selector)
+  }
+
+  pub fn timeout(message: impl Into<String>) -> Self{
+    Self::new(ErrorKind::Timeout, message)
+  }
+
+  pub fn navigation_failed(url: &str, message: impl Into<String>) -> Self{
+    Self::new(ErrorKind::NavigationFailed, message).with_url(url)
+  }
+
+  pub fn with_selector(mut self, selector: impl Into<String>) -> Self{
+    self.selector = Some(selector.into());
+    self
+  }
+
+  pub fn with_url(mut self, url: impl Into<String>) -> Self{
+    self.url = Some(url.into());
+    self
+  }
+}
+
+impl fmt::Display for ErrorKind{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match self{
+      ErrorKind::SelectorNotFound => write!(f, "selector not found"),
+      ErrorKind::Timeout => write!(f, "timeout"),
+      ErrorKind::NavigationFailed => write!(f, "navigation failed"),
+      ErrorKind::ScriptError => write!(f, "script error"),
+      ErrorKind::BrowserCrashed => write!(f, "browser crashed"),
+      ErrorKind::AssertionFailed => write!(f, "assertion failed"),
+      ErrorKind::SetupFailed => write!(f, "setup failed"),
+      ErrorKind::Other => write!(f, "error"),
+    }
+  }
+}
+
+impl fmt::Display for TaskError{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match &self.step_name{
+      Some(step_name) => write!(f, "step '{}' failed: {}", step_name, self.message),
+      None => write!(f, "{}", self.message),
+    }
+  }
+}
+
+impl std::error::Error for TaskError{}
@@ -0,0 +1,70 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+/// what a url, or parts of it, must match. `pattern` is compared with the whole url, `path`
+/// with its pathname, `query` with its search string without the `?` and `hash` with its
+/// fragment without the `#`; every part that is set must match
+#[derive(Debug, Deserialize, Clone, Serialize)]
+pub struct UrlPattern{
+  #[serde(default)]
+  pub pattern: Option<String>,
+  #[serde(default)]
+  pub path: Option<String>,
+  #[serde(default)]
+  pub query: Option<String>,
+  #[serde(default)]
+  pub hash: Option<String>,
+  #[serde(default, rename = "match")]
+  pub mode: UrlMatchMode,
+}
+
+/// `glob` matches the whole value, where `*` stands for anything but `/`, `**` for anything
+/// and `?` for one character; `regex` matches anywhere in it unless anchored
+#[derive(Debug, Deserialize, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrlMatchMode{
+  #[default]
+  Contains,
+  Exact,
+  Regex,
+  Glob,
+}
+
+impl UrlPattern{
+  /// the parts that are set, by the name the page's `URL` object gives them
+  pub fn parts(&self) -> Vec<(&'static str, &str)>{
+    [
+      ("href", &self.pattern),
+      ("pathname", &self.path),
+      ("search", &self.query),
+      ("hash", &self.hash),
+    ].into_iter()
+      .filter_map(|(part, value)| value.as_deref().map(|value| (part, value)))
+      .collect()
+  }
+}
+
+impl fmt::Display for UrlMatchMode{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    match self{
+      UrlMatchMode::Contains => write!(f, "contains"),
+      UrlMatchMode::Exact => write!(f, "exact"),
+      UrlMatchMode::Regex => write!(f, "regex"),
+      UrlMatchMode::Glob => write!(f, "glob"),
+    }
+  }
+}
+
+impl fmt::Display for UrlPattern{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+    let parts = [
+      ("url", &self.pattern),
+      ("path", &self.path),
+      ("query", &self.query),
+      ("hash", &self.hash),
+    ].into_iter()
+      .filter_map(|(part, value)| value.as_ref().map(|value| format!("{} '{}'", part, value)))
+      .collect::<Vec<_>>();
+    write!(f, "{} ({})", parts.join(", "), self.mode)
+  }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use crate::models::element_state::ElementState;
+use crate::models::url_pattern::UrlPattern;
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -17,9 +18,12 @@ pub enum WaitCondition{
     timeout_ms: u64,
     #[serde(default = "default_max_connections")]
     max_connections: u8,
+    #[serde(default = "default_idle_ms")]
+    idle_ms: u64,
   },
   Url{
-    pattern: String,
+    #[serde(flatten)]
+    url: UrlPattern,
     #[serde(default = "default_timeout")]
     timeout_ms: u64,
   },
@@ -28,8 +32,49 @@ pub enum WaitCondition{
     state: ElementState,
     #[serde(default = "default_timeout")]
     timeout_ms: u64,
-  }
+  },
+  /// page text, or the text of `selector`, contains `value`; or no longer does when `absent`
+  Text{
+    value: String,
+    #[serde(default)]
+    selector: Option<String>,
+    #[serde(default)]
+    absent: bool,
+    #[serde(default = "default_timeout")]
+    timeout_ms: u64,
+  },
+  /// a javascript expression, or a function, evaluated until it returns something truthy
+  Function{
+    script: String,
+    #[serde(default = "default_timeout")]
+    timeout_ms: u64,
+  },
+  Count{
+    selector: String,
+    #[serde(default)]
+    equals: Option<usize>,
+    #[serde(default)]
+    min: Option<usize>,
+    #[serde(default)]
+    max: Option<usize>,
+    #[serde(default = "default_timeout")]
+    timeout_ms: u64,
+  },
+  /// no DOM mutations in the page, or inside `selector`, for `idle_ms`
+  DomStable{
+    #[serde(default)]
+    selector: Option<String>,
+    #[serde(default = "default_idle_ms")]
+    idle_ms: u64,
+    #[serde(default = "default_timeout")]
+    timeout_ms: u64,
+  },
+  /// every condition, waited for one after another
+  All{conditions: Vec<WaitCondition>},
+  /// whichever condition is met first
+  Any{conditions: Vec<WaitCondition>},
 }
 
 fn default_timeout() -> u64 {5000}
 fn default_max_connections() -> u8 {2}
+fn default_idle_ms() -> u64 {500}
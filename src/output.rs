@@ -2,7 +2,8 @@ use std::path::{Path, PathBuf};
 use anyhow::{Context, Result};
 use crate::models::dataset_index::DatasetIndex;
 use crate::models::execution_result::ExecutionResult;
-use crate::models::metadata::{StateMetadata, TaskMetadata};
+use crate::models::failure_capture::FailureCapture;
+use crate::models::metadata::{FailureActivity, FailureMetadata, StateMetadata, TaskMetadata};
 use crate::models::task::TaskSummary;
 
 pub struct DatasetWriter{
@@ -29,11 +30,31 @@ impl DatasetWriter{
       StateMetadata{
         step_index: state.step_index,
         step_name: state.step_name.clone(),
+        tab: state.tab,
         filename: format!("{:02}-{}.png", idx+1, slugify(&state.step_name)),
         url: state.url.clone(),
         has_url: state.has_url,
         viewport: state.viewport.clone(),
+        status: state.page_metadata.as_ref().and_then(|page| page.status),
+        redirect_chain: state.page_metadata.as_ref()
+          .map(|page| page.redirect_chain.clone())
+          .unwrap_or_default(),
         context: state.context.clone(),
+        dialogs: state.dialogs.clone(),
+      }
+    }).collect();
+
+    let failures: Vec<FailureMetadata> = result.failures.iter().enumerate().map(|(idx, failure)|{
+      let name = failure_name(idx, failure);
+      FailureMetadata{
+        step_index: failure.step_index,
+        step_name: failure.step_name.clone(),
+        tab: failure.tab,
+        url: failure.url.clone(),
+        timestamp: failure.timestamp.clone(),
+        screenshot: failure.screenshot_base64.as_ref().map(|_| format!("failure/{}.png", name)),
+        dom: failure.dom.as_ref().map(|_| format!("failure/{}.html", name)),
+        activity: format!("failure/{}.json", name),
       }
     }).collect();
 
@@ -44,7 +65,12 @@ impl DatasetWriter{
       success: result.success,
       execution_time_ms: result.execution_time_ms,
       error: result.error.clone(),
+      errors: result.errors.clone(),
+      assertion_failures: result.assertion_failures.clone(),
+      steps: result.steps.clone(),
+      setup_dialogs: result.setup_dialogs.clone(),
       states,
+      failures,
     };
 
     let md_json = serde_json::to_string_pretty(&metadata)?;
@@ -63,10 +89,47 @@ impl DatasetWriter{
         .with_context(|| format!("failed to write screenshot: {}", filename))?;
     }
 
+    if !result.failures.is_empty(){
+      self.save_failures(&task_dir, &result.failures).await?;
+    }
+
     println!("saved {} states to {}", result.captured_states.len(), task_dir.display());
     Ok(())
   }
 
+  async fn save_failures(&self, task_dir: &Path, failures: &[FailureCapture]) -> Result<()>{
+    let failure_dir = task_dir.join("failure");
+    tokio::fs::create_dir_all(&failure_dir)
+      .await
+      .context("failed to create failure directory")?;
+
+    for(idx, failure) in failures.iter().enumerate(){
+      let name = failure_name(idx, failure);
+
+      if let Some(screenshot) = &failure.screenshot_base64{
+        let engine = base64::engine::general_purpose::STANDARD;
+        let image_bytes = base64::engine::Engine::decode(&engine, screenshot.as_bytes())?;
+        tokio::fs::write(failure_dir.join(format!("{}.png", name)), &image_bytes)
+          .await
+          .with_context(|| format!("failed to write failure screenshot: {}.png", name))?;
+      }
+      if let Some(dom) = &failure.dom{
+        tokio::fs::write(failure_dir.join(format!("{}.html", name)), dom)
+          .await
+          .with_context(|| format!("failed to write failure dom: {}.html", name))?;
+      }
+
+      let activity = serde_json::to_string_pretty(&FailureActivity{
+        console: &failure.console,
+        requests: &failure.requests,
+      })?;
+      tokio::fs::write(failure_dir.join(format!("{}.json", name)), activity)
+        .await
+        .with_context(|| format!("failed to write failure activity: {}.json", name))?;
+    }
+    Ok(())
+  }
+
   pub async fn save_batch(&self, results: Vec<ExecutionResult>) -> Result<()>{
     for result in &results{
       self.save_result(result).await?;
@@ -82,12 +145,14 @@ impl DatasetWriter{
       total_tasks: results.len(),
       successful_tasks: results.iter().filter(|r| r.success).count(),
       total_states: results.iter().map(|r| r.captured_states.len()).sum(),
+      total_crashes: results.iter().map(|r| r.crashes).sum(),
       tasks: results.iter().map(|r| TaskSummary{
         task_id: r.task_id.clone(),
         app: r.app.clone(),
         description: r.description.clone(),
         success: r.success,
         state_count: r.captured_states.len(),
+        crashes: r.crashes,
         path: format!("{}/{}", r.app, r.task_id),
       }).collect(),
     };
@@ -102,6 +167,11 @@ impl DatasetWriter{
   }
 }
 
+/// file name, without extension, of the files of a failure capture
+fn failure_name(idx: usize, failure: &FailureCapture) -> String{
+  format!("{:02}-{}", idx+1, slugify(&failure.step_name))
+}
+
 fn slugify(s: &str) -> String{
   s.to_lowercase()
     .chars()
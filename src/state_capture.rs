@@ -8,25 +8,17 @@ use chromiumoxide::{
 use serde::Serialize;
 use tokio::time::sleep;
 use crate::models::{
+  dialog_record::DialogRecord,
   metadata::PageMetadata,
   viewport_info::ViewportInfo
 };
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct CaptureOptions{
   pub full_page: bool,
   pub omit_background: bool,
 }
 
-impl Default for CaptureOptions{
-  fn default() -> Self{
-    Self{
-      full_page: false,
-      omit_background: false,
-    }
-  }
-}
-
 pub async fn capture_screenshot(page: &Page, options: &CaptureOptions) -> Result<Vec<u8>>{
   let mut params = ScreenshotParams::builder();
 
@@ -82,6 +74,8 @@ pub async fn extract_page_metadata(page: &Page) -> Result<PageMetadata>{
     active_element: metadata["activeElement"].as_str().map(String::from),
     has_modals: metadata["hasModals"].as_bool().unwrap_or(false),
     has_overlays: metadata["hasOverlays"].as_bool().unwrap_or(false),
+    status: None,
+    redirect_chain: Vec::new(),
   })
 }
 
@@ -105,3 +99,64 @@ pub async fn capture_settled(page: &Page, settle_ms: u64, options: &CaptureOptio
   wait_for_settle(page, settle_ms).await?;
   capture_screenshot(page, options).await
 }
+
+/// draws a stand-in for a native javascript dialog over the page, showing its message and
+/// the button it was answered with. `backdrop`, a base64 png of the viewport, is shown in place
+/// of the page, so a screenshot taken while the dialog was open can be drawn on
+pub async fn draw_dialog(page: &Page, dialog: &DialogRecord, backdrop: Option<&str>) -> Result<()>{
+  let script = format!(
+    r#"
+    (() => {{
+      const dialog = {};
+      const screenshot = {};
+      const backdrop = document.createElement('div');
+      backdrop.id = '__softlight_dialog';
+      backdrop.style.cssText = 'position:fixed;inset:0;z-index:2147483647;background:rgba(0,0,0,.3);' +
+        'display:flex;align-items:flex-start;justify-content:center;font:14px system-ui,sans-serif';
+      if(screenshot){{
+        backdrop.style.background = 'linear-gradient(rgba(0,0,0,.3),rgba(0,0,0,.3)),' +
+          'url(data:image/png;base64,' + screenshot + ') top left / 100% 100% no-repeat';
+      }}
+      const box = document.createElement('div');
+      box.style.cssText = 'margin-top:24px;min-width:320px;max-width:480px;padding:20px;background:#fff;' +
+        'color:#202124;border-radius:8px;box-shadow:0 4px 16px rgba(0,0,0,.3)';
+      const origin = document.createElement('div');
+      origin.style.cssText = 'font-weight:600;margin-bottom:12px';
+      origin.textContent = location.host + ' says';
+      const message = document.createElement('div');
+      message.style.cssText = 'white-space:pre-wrap;margin-bottom:16px';
+      message.textContent = dialog.message;
+      box.append(origin, message);
+      if(dialog.kind === 'prompt'){{
+        const input = document.createElement('input');
+        input.value = dialog.prompt_text || '';
+        input.style.cssText = 'width:100%;box-sizing:border-box;margin-bottom:16px;padding:6px';
+        box.append(input);
+      }}
+      const buttons = document.createElement('div');
+      buttons.style.cssText = 'display:flex;justify-content:flex-end;gap:8px';
+      const button = (label, chosen) => {{
+        const b = document.createElement('span');
+        b.textContent = label;
+        b.style.cssText = 'padding:6px 16px;border-radius:4px;border:1px solid #1a73e8;' +
+          (chosen ? 'background:#1a73e8;color:#fff' : 'color:#1a73e8');
+        return b;
+      }};
+      if(dialog.kind !== 'alert') buttons.append(button('Cancel', !dialog.accepted));
+      buttons.append(button('OK', dialog.accepted));
+      box.append(buttons);
+      backdrop.append(box);
+      document.documentElement.append(backdrop);
+    }})()
+    "#,
+    serde_json::to_string(dialog)?,
+    serde_json::to_string(&backdrop)?
+  );
+  page.evaluate(script).await?;
+  Ok(())
+}
+
+pub async fn clear_dialog(page: &Page) -> Result<()>{
+  page.evaluate("document.getElementById('__softlight_dialog')?.remove()").await?;
+  Ok(())
+}
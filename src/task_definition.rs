@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde_yaml::Value;
+
+/// whether `yaml` is a task rather than a step fragment meant to be included by one. yaml that
+/// does not parse counts as a task, so loading it reports the error instead of skipping it
+pub fn is_task_document(yaml: &str) -> bool{
+  match serde_yaml::from_str::<Value>(yaml){
+    Ok(document) => document.get("task").is_some_and(Value::is_mapping),
+    Err(_) => true,
+  }
+}
+
+/// parses a task document, replacing every `- include: path` entry of its steps with the
+/// steps of that fragment. paths are relative to the file holding the include, which may pass
+/// `params` that fill the fragment's `${name}` placeholders before task variables are applied.
+/// a fragment is either a list of steps, or a mapping of `steps` and `params` with defaults,
+/// where a null default marks a parameter every include must pass. returns `None` when the
+/// task includes nothing, so it can be parsed from source with its line numbers intact
+pub fn resolve_includes(yaml: &str, path: &Path) -> Result<Option<Value>>{
+  let mut document: Value = serde_yaml::from_str(yaml)
+    .map_err(|e| anyhow::anyhow!("failed to parse task definition: {}", e))?;
+
+  let Some(Value::Sequence(steps)) = document.get_mut("task").and_then(|task| task.get_mut("steps")) else{
+    return Ok(None);
+  };
+  if !steps.iter().any(|step| step.get("include").is_some()){
+    return Ok(None);
+  }
+
+  let mut stack = vec![canonical(path)];
+  *steps = expand(steps, yaml, path, &mut stack)?;
+  Ok(Some(document))
+}
+
+fn expand(steps: &[Value], source: &str, path: &Path, stack: &mut Vec<PathBuf>) -> Result<Vec<Value>>{
+  let lines = include_lines(source);
+  let mut includes = 0;
+  let mut expanded = Vec::with_capacity(steps.len());
+
+  for step in steps{
+    let Some(include) = step.get("include") else{
+      expanded.push(step.clone());
+      continue;
+    };
+
+    let location = match lines.get(includes){
+      Some(line) => format!("{}:{}", path.display(), line),
+      None => path.display().to_string(),
+    };
+    includes += 1;
+
+    let target = include.as_str()
+      .with_context(|| format!("{}: include must be a file path", location))?;
+    let fragment = include_fragment(step, target, path, stack)
+      .with_context(|| format!("{}: failed to include {}", location, target))?;
+    expanded.extend(fragment);
+  }
+  Ok(expanded)
+}
+
+fn include_fragment(step: &Value, target: &str, from: &Path, stack: &mut Vec<PathBuf>) -> Result<Vec<Value>>{
+  let path = from.parent().unwrap_or(Path::new(".")).join(target);
+  let key = path.canonicalize()
+    .with_context(|| format!("fragment not found: {}", path.display()))?;
+
+  if stack.contains(&key){
+    let cycle = stack.iter()
+      .chain(std::iter::once(&key))
+      .map(|p| p.display().to_string())
+      .collect::<Vec<_>>()
+      .join(" -> ");
+    anyhow::bail!("include cycle: {}", cycle);
+  }
+
+  let source = fs::read_to_string(&path)
+    .with_context(|| format!("failed to read fragment: {}", path.display()))?;
+  let fragment: Value = serde_yaml::from_str(&source)
+    .map_err(|e| anyhow::anyhow!("failed to parse {}: {}", path.display(), e))?;
+
+  let (steps, declared) = match &fragment{
+    Value::Sequence(steps) => (steps.clone(), None),
+    Value::Mapping(_) => match fragment.get("steps"){
+      Some(Value::Sequence(steps)) => (steps.clone(), fragment.get("params")),
+      _ => anyhow::bail!("{} has no list of steps", path.display()),
+    },
+    _ => anyhow::bail!("{} is not a list of steps", path.display()),
+  };
+
+  let params = params(step.get("params"), declared)?;
+  let steps: Vec<Value> = steps.iter().map(|s| substitute(s, &params)).collect();
+
+  stack.push(key);
+  let expanded = expand(&steps, &source, &path, stack);
+  stack.pop();
+  expanded
+}
+
+/// the include's params over the fragment's defaults, failing on any missing required one
+fn params(given: Option<&Value>, declared: Option<&Value>) -> Result<HashMap<String, Value>>{
+  let mut params = HashMap::new();
+
+  if let Some(declared) = declared{
+    let declared = declared.as_mapping().context("fragment params must be a mapping of defaults")?;
+    for (name, default) in declared{
+      let name = name.as_str().context("fragment param names must be strings")?;
+      params.insert(name.to_string(), default.clone());
+    }
+  }
+  if let Some(given) = given{
+    let given = given.as_mapping().context("include params must be a mapping")?;
+    for (name, value) in given{
+      let name = name.as_str().context("include param names must be strings")?;
+      params.insert(name.to_string(), value.clone());
+    }
+  }
+
+  let mut missing: Vec<_> = params.iter()
+    .filter(|(_, value)| value.is_null())
+    .map(|(name, _)| name.as_str())
+    .collect();
+  if !missing.is_empty(){
+    missing.sort();
+    anyhow::bail!("missing params: {}", missing.join(", "));
+  }
+  Ok(params)
+}
+
+/// fills `${name}` placeholders for the given params in every string of `value`, leaving
+/// other placeholders and `$${` for the task variables. a string that is a single placeholder
+/// takes the param's value as is, so numbers and booleans keep their type
+fn substitute(value: &Value, params: &HashMap<String, Value>) -> Value{
+  match value{
+    Value::String(text) => {
+      if let Some(name) = text.strip_prefix("${").and_then(|t| t.strip_suffix('}'))
+        && let Some(param) = params.get(name)
+      {
+        return param.clone();
+      }
+      Value::String(substitute_str(text, params))
+    }
+    Value::Sequence(items) => Value::Sequence(items.iter().map(|v| substitute(v, params)).collect()),
+    Value::Mapping(mapping) => Value::Mapping(
+      mapping.iter().map(|(k, v)| (k.clone(), substitute(v, params))).collect()
+    ),
+    Value::Tagged(tagged) => Value::Tagged(Box::new(serde_yaml::value::TaggedValue{
+      tag: tagged.tag.clone(),
+      value: substitute(&tagged.value, params),
+    })),
+    Value::Null | Value::Bool(_) | Value::Number(_) => value.clone(),
+  }
+}
+
+fn substitute_str(text: &str, params: &HashMap<String, Value>) -> String{
+  let mut out = String::with_capacity(text.len());
+  let mut rest = text;
+
+  while let Some(start) = rest.find('$'){
+    out.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    if let Some(escaped) = rest.strip_prefix("$${"){
+      out.push_str("$${");
+      rest = escaped;
+      continue;
+    }
+    let replaced = rest.strip_prefix("${")
+      .and_then(|tail| tail.find('}').map(|end| (&tail[..end], &tail[end + 1..])))
+      .and_then(|(name, tail)| params.get(name).map(|param| (scalar(param), tail)));
+    match replaced{
+      Some((param, tail)) => {
+        out.push_str(&param);
+        rest = tail;
+      }
+      None => {
+        out.push('$');
+        rest = &rest[1..];
+      }
+    }
+  }
+  out.push_str(rest);
+  out
+}
+
+fn scalar(value: &Value) -> String{
+  match value{
+    Value::String(text) => text.clone(),
+    Value::Bool(b) => b.to_string(),
+    Value::Number(n) => n.to_string(),
+    other => serde_yaml::to_string(other).unwrap_or_default().trim_end().to_string(),
+  }
+}
+
+/// 1-based line numbers of the `include:` keys in `source`, in document order
+fn include_lines(source: &str) -> Vec<usize>{
+  source.lines()
+    .enumerate()
+    .filter(|(_, line)| {
+      let line = line.trim_start();
+      let line = line.strip_prefix('-').map(str::trim_start).unwrap_or(line);
+      let line = line.strip_prefix('{').map(str::trim_start).unwrap_or(line);
+      line.starts_with("include:")
+    })
+    .map(|(idx, _)| idx + 1)
+    .collect()
+}
+
+fn canonical(path: &Path) -> PathBuf{
+  path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests{
+  use super::*;
+
+  /// a fresh directory holding `files`, removed again once the test is done
+  struct Fixture{
+    dir: PathBuf,
+  }
+
+  impl Fixture{
+    fn new(name: &str, files: &[(&str, &str)]) -> Self{
+      let dir = std::env::temp_dir().join(format!("softlight-includes-{}-{}", std::process::id(), name));
+      let _ = fs::remove_dir_all(&dir);
+      for (file, contents) in files{
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+      }
+      Self{dir}
+    }
+
+    fn resolve(&self, file: &str) -> Result<Option<Value>>{
+      let path = self.dir.join(file);
+      resolve_includes(&fs::read_to_string(&path).unwrap(), &path)
+    }
+  }
+
+  impl Drop for Fixture{
+    fn drop(&mut self){
+      let _ = fs::remove_dir_all(&self.dir);
+    }
+  }
+
+  fn step_names(document: &Value) -> Vec<String>{
+    document["task"]["steps"].as_sequence().unwrap().iter()
+      .map(|step| step["name"].as_str().unwrap().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn tasks_without_includes_are_left_to_parse_from_source(){
+    let fixture = Fixture::new("none", &[("task.yaml", "task:\n  steps:\n    - name: open\n")]);
+    assert!(fixture.resolve("task.yaml").unwrap().is_none());
+  }
+
+  #[test]
+  fn includes_are_replaced_by_the_fragment_steps_in_place(){
+    let fixture = Fixture::new("nested", &[
+      ("task.yaml", "task:\n  steps:\n    - name: first\n    - include: parts/login.yaml\n    - name: last\n"),
+      ("parts/login.yaml", "- name: login\n- include: ../shared/submit.yaml\n"),
+      ("shared/submit.yaml", "- name: submit\n"),
+    ]);
+    let document = fixture.resolve("task.yaml").unwrap().unwrap();
+    assert_eq!(step_names(&document), ["first", "login", "submit", "last"]);
+  }
+
+  #[test]
+  fn params_fill_placeholders_over_defaults(){
+    let fixture = Fixture::new("params", &[
+      ("task.yaml", "task:\n  steps:\n    - include: fill.yaml\n      params:\n        field: email\n        delay: 250\n"),
+      (
+        "fill.yaml",
+        "params:\n  field: ~\n  delay: 100\n  value: fallback\nsteps:\n  - name: fill ${field} with ${value} and $${user}\n    wait: ${delay}\n    other: ${unknown}\n",
+      ),
+    ]);
+    let document = fixture.resolve("task.yaml").unwrap().unwrap();
+    let step = &document["task"]["steps"][0];
+    assert_eq!(step["name"].as_str(), Some("fill email with fallback and $${user}"));
+    assert_eq!(step["wait"].as_u64(), Some(250));
+    assert_eq!(step["other"].as_str(), Some("${unknown}"));
+  }
+
+  #[test]
+  fn required_params_must_be_passed(){
+    let fixture = Fixture::new("required", &[
+      ("task.yaml", "task:\n  steps:\n    - include: fill.yaml\n"),
+      ("fill.yaml", "params:\n  field: ~\n  value: ~\nsteps:\n  - name: fill ${field}\n"),
+    ]);
+    let error = format!("{:#}", fixture.resolve("task.yaml").unwrap_err());
+    assert!(error.contains("missing params: field, value"), "{}", error);
+  }
+
+  #[test]
+  fn include_cycles_are_reported_with_their_path(){
+    let fixture = Fixture::new("cycle", &[
+      ("task.yaml", "task:\n  steps:\n    - include: a.yaml\n"),
+      ("a.yaml", "- include: b.yaml\n"),
+      ("b.yaml", "- include: a.yaml\n"),
+    ]);
+    let error = format!("{:#}", fixture.resolve("task.yaml").unwrap_err());
+    assert!(error.contains("include cycle:"), "{}", error);
+    assert!(error.contains("a.yaml -> ") && error.contains("b.yaml -> "), "{}", error);
+  }
+
+  #[test]
+  fn missing_fragments_are_reported_at_the_include_line(){
+    let fixture = Fixture::new("missing", &[
+      ("task.yaml", "task:\n  steps:\n    - name: open\n    - include: gone.yaml\n"),
+    ]);
+    let error = format!("{:#}", fixture.resolve("task.yaml").unwrap_err());
+    assert!(error.contains("task.yaml:4: failed to include gone.yaml"), "{}", error);
+    assert!(error.contains("fragment not found"), "{}", error);
+  }
+
+  #[test]
+  fn unparsable_yaml_counts_as_a_task(){
+    assert!(is_task_document("task:\n  steps: [\n"));
+    assert!(is_task_document("task:\n  steps: []\n"));
+    assert!(!is_task_document("- name: fragment step\n"));
+  }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use anyhow::{Context, Result};
+use crate::models::{
+  action::Action,
+  assertion::Assertion,
+  dialog_policy::DialogPolicy,
+  frame_selector::FrameSelector,
+  key_sequence::KeySequence,
+  mouse_target::MouseTarget,
+  step_wait::StepWait,
+  tab_selector::TabSelector,
+  url_pattern::UrlPattern,
+  wait_condition::WaitCondition,
+};
+
+/// values available to `${...}` placeholders in task fields: `${env.NAME}` reads the process
+/// environment, `${run.timestamp}` is the task start time in unix milliseconds, and any other
+/// name refers to a task `vars` entry or a value stored by an `extract` step. `$${` is a literal `${`
+#[derive(Debug)]
+pub struct Variables{
+  values: HashMap<String, String>,
+}
+
+impl Variables{
+  pub fn new(vars: &HashMap<String, String>) -> Self{
+    let mut values = vars.clone();
+    values.insert("run.timestamp".to_string(), chrono::Utc::now().timestamp_millis().to_string());
+    Self{values}
+  }
+
+  pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>){
+    self.values.insert(name.into(), value.into());
+  }
+
+  pub fn get(&self, name: &str) -> Result<String>{
+    if let Some(var) = name.strip_prefix("env."){
+      return std::env::var(var).with_context(|| format!("environment variable not set: {}", var));
+    }
+    self.values.get(name)
+      .cloned()
+      .with_context(|| format!("undefined variable: {}", name))
+  }
+
+  pub fn render(&self, template: &str) -> Result<String>{
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('$'){
+      output.push_str(&rest[..start]);
+      rest = &rest[start..];
+
+      if let Some(after) = rest.strip_prefix("$${"){
+        output.push_str("${");
+        rest = after;
+      }else if let Some(after) = rest.strip_prefix("${"){
+        let end = after.find('}')
+          .with_context(|| format!("unterminated placeholder in '{}'", template))?;
+        output.push_str(&self.get(after[..end].trim())?);
+        rest = &after[end+1..];
+      }else{
+        output.push('$');
+        rest = &rest[1..];
+      }
+    }
+
+    output.push_str(rest);
+    Ok(output)
+  }
+}
+
+/// substitutes variables into the string fields of a task value; scripts are left untouched
+/// since `${...}` is valid javascript
+pub trait Template: Sized{
+  fn render(&self, vars: &Variables) -> Result<Self>;
+}
+
+impl Template for String{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    vars.render(self)
+  }
+}
+
+impl<T: Template> Template for Option<T>{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    self.as_ref().map(|value| value.render(vars)).transpose()
+  }
+}
+
+impl Template for KeySequence{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(match self{
+      KeySequence::Single(chord) => KeySequence::Single(chord.render(vars)?),
+      KeySequence::Sequence(chords) => KeySequence::Sequence(
+        chords.iter().map(|chord| chord.render(vars)).collect::<Result<_>>()?
+      ),
+    })
+  }
+}
+
+impl Template for MouseTarget{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(MouseTarget{
+      selector: self.selector.render(vars)?,
+      position: self.position,
+    })
+  }
+}
+
+impl Template for Action{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(match self{
+      Action::Navigate{url, navigation} => Action::Navigate{
+        url: url.render(vars)?,
+        navigation: navigation.clone(),
+      },
+      Action::Click{target, wait_before_ms, button, click_count} => Action::Click{
+        target: target.render(vars)?,
+        wait_before_ms: *wait_before_ms,
+        button: *button,
+        click_count: *click_count,
+      },
+      Action::DoubleClick{target} => Action::DoubleClick{target: target.render(vars)?},
+      Action::RightClick{target} => Action::RightClick{target: target.render(vars)?},
+      Action::MouseDown{target, button} => Action::MouseDown{target: target.render(vars)?, button: *button},
+      Action::MouseUp{target, button} => Action::MouseUp{target: target.render(vars)?, button: *button},
+      Action::Drag{from, to, steps} => Action::Drag{
+        from: from.render(vars)?,
+        to: to.render(vars)?,
+        steps: *steps,
+      },
+      Action::Type{selector, value, clear_first} => Action::Type{
+        selector: selector.render(vars)?,
+        value: value.render(vars)?,
+        clear_first: *clear_first,
+      },
+      Action::Hover{selector} => Action::Hover{selector: selector.render(vars)?},
+      Action::Select{selector, value, label, index} => Action::Select{
+        selector: selector.render(vars)?,
+        value: value.iter().map(|v| v.render(vars)).collect::<Result<_>>()?,
+        label: label.iter().map(|l| l.render(vars)).collect::<Result<_>>()?,
+        index: index.clone(),
+      },
+      Action::Check{selector} => Action::Check{selector: selector.render(vars)?},
+      Action::Uncheck{selector} => Action::Uncheck{selector: selector.render(vars)?},
+      Action::Upload{selector, files} => Action::Upload{
+        selector: selector.render(vars)?,
+        files: files.iter().map(|f| f.render(vars)).collect::<Result<_>>()?,
+      },
+      Action::Press{key, repeat, selector, delay_ms} => Action::Press{
+        key: key.render(vars)?,
+        repeat: *repeat,
+        selector: selector.render(vars)?,
+        delay_ms: *delay_ms,
+      },
+      Action::Extract{into, selector, attribute, script} => Action::Extract{
+        into: into.clone(),
+        selector: selector.render(vars)?,
+        attribute: attribute.render(vars)?,
+        script: script.clone(),
+      },
+      Action::SwitchTab{tab} => Action::SwitchTab{
+        tab: TabSelector{
+          index: tab.index,
+          url: tab.url.render(vars)?,
+          latest: tab.latest,
+        },
+      },
+      Action::Wait{..} | Action::Scroll{..} | Action::Execute{..}
+        | Action::WaitForPopup{..} | Action::CloseTab{..} => self.clone(),
+    })
+  }
+}
+
+impl Template for WaitCondition{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(match self{
+      WaitCondition::Selector{value, timeout_ms, visible} => WaitCondition::Selector{
+        value: value.render(vars)?,
+        timeout_ms: *timeout_ms,
+        visible: *visible,
+      },
+      WaitCondition::Url{url, timeout_ms} => WaitCondition::Url{
+        url: url.render(vars)?,
+        timeout_ms: *timeout_ms,
+      },
+      WaitCondition::Element{selector, state, timeout_ms} => WaitCondition::Element{
+        selector: selector.render(vars)?,
+        state: state.clone(),
+        timeout_ms: *timeout_ms,
+      },
+      WaitCondition::Text{value, selector, absent, timeout_ms} => WaitCondition::Text{
+        value: value.render(vars)?,
+        selector: selector.render(vars)?,
+        absent: *absent,
+        timeout_ms: *timeout_ms,
+      },
+      WaitCondition::Count{selector, equals, min, max, timeout_ms} => WaitCondition::Count{
+        selector: selector.render(vars)?,
+        equals: *equals,
+        min: *min,
+        max: *max,
+        timeout_ms: *timeout_ms,
+      },
+      WaitCondition::DomStable{selector, idle_ms, timeout_ms} => WaitCondition::DomStable{
+        selector: selector.render(vars)?,
+        idle_ms: *idle_ms,
+        timeout_ms: *timeout_ms,
+      },
+      WaitCondition::All{conditions} => WaitCondition::All{
+        conditions: conditions.iter().map(|c| c.render(vars)).collect::<Result<_>>()?,
+      },
+      WaitCondition::Any{conditions} => WaitCondition::Any{
+        conditions: conditions.iter().map(|c| c.render(vars)).collect::<Result<_>>()?,
+      },
+      WaitCondition::Duration{..} | WaitCondition::NetworkIdle{..}
+        | WaitCondition::Function{..} => self.clone(),
+    })
+  }
+}
+
+impl Template for UrlPattern{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(UrlPattern{
+      pattern: self.pattern.render(vars)?,
+      path: self.path.render(vars)?,
+      query: self.query.render(vars)?,
+      hash: self.hash.render(vars)?,
+      mode: self.mode,
+    })
+  }
+}
+
+impl Template for StepWait{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(StepWait{
+      condition: self.condition.render(vars)?,
+      frame: self.frame.render(vars)?,
+    })
+  }
+}
+
+impl Template for FrameSelector{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(FrameSelector{
+      selector: self.selector.render(vars)?,
+      name: self.name.render(vars)?,
+      url: self.url.render(vars)?,
+    })
+  }
+}
+
+impl Template for DialogPolicy{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(DialogPolicy{
+      action: self.action,
+      text: self.text.render(vars)?,
+      capture: self.capture,
+    })
+  }
+}
+
+impl Template for Assertion{
+  fn render(&self, vars: &Variables) -> Result<Self>{
+    Ok(match self{
+      Assertion::Text{value, selector} => Assertion::Text{
+        value: value.render(vars)?,
+        selector: selector.render(vars)?,
+      },
+      Assertion::Count{selector, equals, min, max} => Assertion::Count{
+        selector: selector.render(vars)?,
+        equals: *equals,
+        min: *min,
+        max: *max,
+      },
+      Assertion::Attribute{selector, name, value} => Assertion::Attribute{
+        selector: selector.render(vars)?,
+        name: name.clone(),
+        value: value.render(vars)?,
+      },
+      Assertion::Url{url} => Assertion::Url{url: url.render(vars)?},
+      Assertion::Title{value} => Assertion::Title{value: value.render(vars)?},
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests{
+  use super::*;
+
+  fn vars() -> Variables{
+    let mut vars = Variables::new(&HashMap::from([("user".to_string(), "ada".to_string())]));
+    vars.set("order", "42");
+    vars
+  }
+
+  #[test]
+  fn placeholders_are_replaced_by_their_values(){
+    let vars = vars();
+    assert_eq!(vars.render("no placeholders").unwrap(), "no placeholders");
+    assert_eq!(vars.render("/users/${user}/orders/${ order }").unwrap(), "/users/ada/orders/42");
+    assert_eq!(vars.render("${user}${order}").unwrap(), "ada42");
+    assert!(vars.render("${run.timestamp}").unwrap().parse::<i64>().is_ok());
+  }
+
+  #[test]
+  fn escaped_placeholders_are_kept_without_looking_them_up(){
+    let vars = vars();
+    assert_eq!(vars.render("$${user}").unwrap(), "${user}");
+    assert_eq!(vars.render("$${undefined} and ${user}").unwrap(), "${undefined} and ada");
+    assert_eq!(vars.render("$${a}$${b}").unwrap(), "${a}${b}");
+    assert_eq!(vars.render("unclosed $${").unwrap(), "unclosed ${");
+  }
+
+  #[test]
+  fn a_lone_dollar_is_literal(){
+    let vars = vars();
+    assert_eq!(vars.render("costs $5 or $").unwrap(), "costs $5 or $");
+    assert_eq!(vars.render("$$ ${user}").unwrap(), "$$ ada");
+  }
+
+  #[test]
+  fn undefined_and_unterminated_placeholders_fail(){
+    let vars = vars();
+    let error = vars.render("hello ${nobody}").unwrap_err().to_string();
+    assert_eq!(error, "undefined variable: nobody");
+    let error = vars.render("hello ${user").unwrap_err().to_string();
+    assert_eq!(error, "unterminated placeholder in 'hello ${user'");
+    let error = vars.render("${env.SOFTLIGHT_SURELY_UNSET_VARIABLE}").unwrap_err().to_string();
+    assert_eq!(error, "environment variable not set: SOFTLIGHT_SURELY_UNSET_VARIABLE");
+  }
+}